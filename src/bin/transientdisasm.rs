@@ -0,0 +1,148 @@
+//! Transient disassembler
+//! Reads a transient image and prints every instruction of its code section in TransientAssembly
+//! syntax, prefixed by its byte offset. Variables are written by the name in the symbol table of the
+//! image (`$counter`), or by their address (`$16384`) if it has none, and tags as the address they
+//! point to (`#16`). Bytes after the code section are printed as data.
+//!
+//! Usage: transientdisasm <image> [--hex]
+//!
+//! Offsets are addresses in transient memory, so the header of the image isn't counted.
+
+use std::collections::BTreeMap;
+use std::env::args;
+use std::fs::File;
+use std::io::Read;
+use std::process::exit;
+use transient_asm::image::{read_image, read_symbols};
+use transient_asm::vm::{find_operation, OperandLayout, DELAY_SLOT_FLAG, PREDICATE_FLAG};
+
+const ADDRESS_SIZE: usize = transient_asm::vm::ADDRESS_SIZE as usize;
+
+/// Returns the prefix of an operand in TransientAssembly: `#` for tags, `$` for variables, and
+/// nothing for literals
+fn operand_prefix(mnemonic: &str, index: usize) -> &'static str {
+    match (mnemonic, index) {
+        ("jmp" | "jie" | "jne" | "call", 0) | ("switch", 3) | ("bloopn", 1) => "#",
+        ("syscall" | "profr", 0) => "",
+        _ => "$",
+    }
+}
+
+/// Writes the mnemonic of a sized instruction with its flags and size suffix, e.g. `?add64`
+fn sized_mnemonic(mnemonic: &str, size: u8) -> String {
+    let predicate = if size & PREDICATE_FLAG != 0 { "?" } else { "" };
+    let delay_slot = if size & DELAY_SLOT_FLAG != 0 { "d" } else { "" };
+    // JMP doesn't encode its size, but the compiler still expects a suffix
+    let bits = match size & !(PREDICATE_FLAG | DELAY_SLOT_FLAG) {
+        0 => 64,
+        size => size as usize * 8,
+    };
+    format!("{}{}{}{}", predicate, mnemonic, delay_slot, bits)
+}
+
+fn address_decode(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..ADDRESS_SIZE].try_into().expect("[Disasm]: Address decode failed: Couldn't parse instruction slice"))
+}
+
+/// Decodes the instruction at the start of `bytes` into its length and its TransientAssembly
+/// tokens, or returns None if the bytes don't hold a complete instruction. Variables found in
+/// `symbols` are written by name.
+fn disassemble(bytes: &[u8], symbols: &BTreeMap<u32, String>) -> Option<(usize, Vec<String>)> {
+    let opcode = *bytes.first()?;
+    let (_, mnemonic, layout) = find_operation(opcode)?;
+    let instruction = bytes.get(..layout.length(*bytes.get(1).unwrap_or(&0)))?;
+    let operand = |index: usize, offset: usize| {
+        let prefix = operand_prefix(mnemonic, index);
+        let address = address_decode(&instruction[offset..]);
+        match symbols.get(&address) {
+            Some(name) if prefix == "$" => format!("${}", name),
+            _ => format!("{}{}", prefix, address),
+        }
+    };
+    let mut tokens = vec![];
+    match layout {
+        OperandLayout::Standard(used) => {
+            tokens.push(sized_mnemonic(mnemonic, instruction[1]));
+            for (index, position) in used.iter().enumerate() {
+                tokens.push(operand(index, 2 + position * ADDRESS_SIZE));
+            }
+        }
+        OperandLayout::Sized(count) => {
+            tokens.push(sized_mnemonic(mnemonic, instruction[1]));
+            for index in 0..*count {
+                tokens.push(operand(index, 2 + index * ADDRESS_SIZE));
+            }
+        }
+        OperandLayout::Unsized(count) => {
+            tokens.push(mnemonic.to_string());
+            for index in 0..*count {
+                tokens.push(operand(index, 1 + index * ADDRESS_SIZE));
+            }
+        }
+        OperandLayout::TableLookup => {
+            tokens.push(mnemonic.to_string());
+            tokens.push(operand(0, 1));
+            tokens.push(operand(1, 5));
+            tokens.push(instruction[9].to_string());
+            tokens.push(operand(3, 10));
+        }
+        OperandLayout::Immediate => {
+            let size = instruction.len() - 2 - ADDRESS_SIZE;
+            let value = instruction[2..][..size].iter().fold(0u64, |value, byte| value << 8 | *byte as u64);
+            tokens.push(sized_mnemonic(mnemonic, instruction[1]));
+            tokens.push(value.to_string());
+            tokens.push(operand(1, 2 + size));
+        }
+    }
+    Some((instruction.len(), tokens))
+}
+
+/// Reports a usage error and exits
+fn stop(message: &str) -> ! {
+    eprintln!("Stop: {}", message);
+    exit(1);
+}
+
+fn main() {
+    // Verify input arguments
+    let args: Vec<String> = args().collect();
+    if args.len() < 2 {
+        stop("Incorrect amount of arguments!");
+    }
+    let hex = args[2..].iter().any(|x| x == "--hex");
+
+    // Read image
+    let mut file_contents: Vec<u8> = vec![];
+    let read = File::open(&args[1]).and_then(|mut file| file.read_to_end(&mut file_contents));
+    if read.is_err() {
+        stop("Failed to read file");
+    }
+    let (header, body) = match read_image(&file_contents) {
+        Ok(x) => x,
+        Err(error) => stop(&error.to_string()),
+    };
+    let image = &body[..header.image_size];
+    let code_end = header.code_length;
+    let symbols: BTreeMap<u32, String> = read_symbols(&file_contents)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, address, _size)| (address as u32, name))
+        .collect();
+
+    let mut offset = 0;
+    while let Some((length, tokens)) = disassemble(&image[offset..code_end], &symbols) {
+        if hex {
+            let bytes: Vec<String> = image[offset..][..length].iter().map(|x| format!("{:02x}", x)).collect();
+            println!("0x{:04X}  {:<41}  {}", offset, bytes.join(" "), tokens.join("  "));
+        } else {
+            println!("0x{:04X}  {}", offset, tokens.join("  "));
+        }
+        offset += length;
+    }
+    for (address, byte) in image.iter().enumerate().skip(offset) {
+        match symbols.get(&(address as u32)) {
+            Some(name) => println!("data  0x{:04X}  0x{:02X}  ${}", address, byte, name),
+            None => println!("data  0x{:04X}  0x{:02X}", address, byte),
+        }
+    }
+}
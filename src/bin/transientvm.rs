@@ -1,363 +1,142 @@
-//! Transient is, in essence, a custom virtual machine and file format. The transient processor
-//! loads a transient "image' into the virtual address space and begins execution at offset 0x00.
-//!
-//!
-//! # Opcodes
-//! - 0x01: MOV byte from source1 into destination
-//! - 0x02: ADD source1 and source2 and store result in destination
-//! - 0x03: SUB source2 from source1 and store result in destination
-//! - 0x04: MUL source1 and source2 and store result in destination
-//! - 0x05: DIV source1 by source2 and store result in destination (truncated)
-//! - 0x06: DIV source1 by source2 and store result in destination (rounded)
-//! - 0x07: REM divides source1 by source2 and stores the remainder in destination
-//! - 0x08: CGT compare if source1 is greater than source2, and if so, store 1 in destination
-//! - 0x09: CLT compare if source1 is less than source2, and if so, store 1 in destination
-//! - 0x0A: JMP stops current execution and jumps to code in source1
-//! - 0x0B: JIE stops current execution and jumps to code in source1 ONLY IF source2 is non-zero
-//! - 0x0C: JNE stops current execution and jumps to code in source1 ONLY IF source2 is zero
-//! - 0x0D: PUT prints data at source1 to the screen (int)
-//! - 0x0E: PUT prints data at source1 to the screen (char)
-//! - 0x0F: IMZ gets the image size that was loaded to ROM and stores it in destination
-//! - 0x10: EQU compare if source1 and source2 are equal, and if so, store 1 in destination
-//! - 0xFF: HLT halts execution and stops processor
-//!
-//! # Transient addresses
-//! Source1, source2, and destination are transient addresses. These can range from 0 up to TRANSIENT_MEM_MAX. Do note, however, that the transient processor will
-//! fill the transient memory with program data up to the programs length. To get the length of the
-//! program, see opcodes above.
-
-/*
-Mov
-Layout: opcode ptr_mode add_size arg_1 arg_2
-Opcode: 0x01
-Description: Sets arg_1 to arg_2
-
-Add
-Layout: opcode ptr_mode add_size arg_1 arg_2 arg_3
-Opcode: 0x02
-Description: Adds arg_1 and arg_2 and stores in arg_3
-
-Sub
-Layout: opcode ptr_mode add_size arg_1 arg_2 arg_3
-Opcode: 0x03
-Description: Subtracts arg_2 from arg_1 and stores in arg_3
-
-Mul
-Layout: opcode ptr_mode add_size arg_1 arg_2 arg_3
-Opcode: 0x04
-Description: Multiplies arg_1 and arg_2 and stores in arg_3
-
-Div
-Layout: opcode ptr_mode add_size arg_1 arg_2 arg_3
-Opcode: 0x05
-Description: Divides arg_1 by arg_2 and stores quotient in arg_3
-
-Rem
-Layout: opcode ptr_mode add_size arg_1 arg_2 arg_3
-Opcode: 0x06
-Description: Divides arg_1 and arg_2 and stores remainder in arg_3
-
-Equ
-Layout: opcode ptr_mode add_size arg_1 arg_2 arg_3
-Opcode: 0x07
-Description: If arg_1 is equal to arg_2, store 0x1 in arg_3, otherwise store 0x0
-
-Cgt
-Layout: opcode ptr_mode add_size arg_1 arg_2 arg_3
-Opcode: 0x08
-Description: If arg_1 is greater than arg_2, store 0x1 in arg_3, otherwise store 0x0
-
-Clt
-Layout: opcode ptr_mode add_size arg_1 arg_2 arg_3
-Opcode: 0x09
-Description: If arg_1 is less than arg_2, store 0x1 in arg_3, otherwise store 0x0
-
-Jmp
-Layout: opcode ptr_mode arg_1
-Opcode: 0x0A
-Description: Set program counter to arg_1, effectively jumping to arg_1
-
-Jie
-Layout: opcode ptr_mode add_size arg_1 arg_2
-Opcode: 0x0B
-Description: Set program counter to arg_2 if arg_1 is 0x1.
-
-Jne
-Layout: opcode ptr_mode add_size arg_1 arg_2
-Opcode: 0x0C
-Description: Set program counter to arg_2 if arg_1 is 0x0.
-
-PutI
-Layout: opcode ptr_mode add_size arg_1
-Opcode: 0x0D
-Description: Print arg_1 to the console as an integer.
-
-PutC
-Layout: opcode ptr_mode add_size arg_1
-Opcode: 0x0E
-Description: Print arg_1 to the console as an ascii character.
-
-Imz
-Layout: opcode ptr_mode add_size arg_1
-Opcode: 0x0F
-Description: Invokes the image size (in bytes) from the virtual machine and stores it in arg_1
-
-Hlt
-Layout: opcode
-Opcode: 0xFF
-Description: Halts execution and exits the virtual machine
-*/
-
-const MOV: u8 = 0x01;
-const ADD: u8 = 0x02;
-const SUB: u8 = 0x03;
-const MUL: u8 = 0x04;
-const DIV: u8 = 0x05;
-const REM: u8 = 0x06;
-const EQU: u8 = 0x07;
-const CGT: u8 = 0x08;
-const CLT: u8 = 0x09;
-const JMP: u8 = 0x0A;
-const JIE: u8 = 0x0B;
-const JNE: u8 = 0x0C;
-const PUT_I: u8 = 0x0D;
-const PUT_C: u8 = 0x0E;
-const IMZ: u8 = 0x0F;
-const HLT: u8 = 0xFF;
+//! Virtual machine that loads a transient image and executes it. See `transient_asm::vm` for the
+//! instruction set.
 
 use std::env::args;
 use std::fs::File;
 use std::io::Read;
-
-const TRANSIENT_MEM_MAX: usize = 0xFFFF;
-
-#[derive(PartialEq)]
-pub enum TransientMode {
-    RUNNING,
-    HALTED,
+use std::process::exit;
+use std::thread;
+use transient_asm::error::TransientError;
+use transient_asm::image::{read_debug_info, read_metadata};
+use transient_asm::vm::{TransientState, TRANSIENT_MEM_MAX};
+
+/// Reports a usage error and exits
+fn stop(message: &str) -> ! {
+    eprintln!("Stop: {}", message);
+    exit(1);
 }
 
-pub struct TransientState<const TRANSIENT_MEM_MAX: usize> {
-    pub memory: Vec<u8>,
-    pub memory_limit: usize,
-    pub image_length: usize, // Length of executable code in memory
-    pub program_counter: usize,
-    pub mode: TransientMode,
+/// Reports an error raised by the processor and exits
+fn halt(error: TransientError) -> ! {
+    eprintln!("[Halt]: {}", error);
+    exit(1);
 }
 
-impl<const TRANSIENT_MEM_MAX: usize> TransientState<TRANSIENT_MEM_MAX> {
-    /// Initialize a new, empty instance of a transient processor/state with a transient memory
-    /// size of TRANSIENT_MEM_MAX bytes.
-    pub fn new() -> Self {
-        TransientState {
-            memory: vec![],
-            memory_limit: TRANSIENT_MEM_MAX,
-            image_length: 0,
-            program_counter: 0,
-            mode: TransientMode::HALTED,
-        }
-    }
-    /// Loads a transient memory image into a state/processor at a specified offset.
-    pub fn load_image(&mut self, offset: usize, image: &[u8]) {
-        // Allocate space for image and set it to 0x00
-        self.memory.resize(image.len(), 0x00);
-        // Copy over image data
-        self.memory[offset..image.len() + offset].copy_from_slice(image);
-        // Set image lengt of processor data
-        self.image_length = image.len();
-    }
-    /// Starts a loop that runs the processor until halted
-    pub fn run(&mut self, start: usize) {
-        self.program_counter = start;
-        self.mode = TransientMode::RUNNING;
-        while self.mode == TransientMode::RUNNING {
-            let instruction = self.resolve_instruction(self.program_counter);
-            self.program_counter = self.execute_instruction(&instruction);
+/// Reports an error raised while executing the instruction at `pc`, along with its source line and
+/// the hash of its source file if the image has debug info, and exits
+fn halt_at(error: TransientError, pc: usize, debug_info: &[(usize, usize, u32)]) -> ! {
+    match debug_info.binary_search_by_key(&pc, |(address, ..)| *address) {
+        Ok(index) => {
+            let (_, line_number, file_hash) = debug_info[index];
+            eprintln!("[Halt]: {} at {:#06X}, source line {} of file {:08X}", error, pc, line_number, file_hash)
         }
+        Err(_) => eprintln!("[Halt]: {} at {:#06X}", error, pc),
     }
-    pub fn resolve_instruction(&self, base_ptr: usize) -> Vec<u8> {
-        // Fetch correct number of bytes depending on instruction
-        match self.memory[base_ptr] {
-            MOV => &self.memory[base_ptr..][..5],
-            ADD => &self.memory[base_ptr..][..6],
-            SUB => &self.memory[base_ptr..][..6],
-            MUL => &self.memory[base_ptr..][..6],
-            DIV => &self.memory[base_ptr..][..6],
-            REM => &self.memory[base_ptr..][..6],
-            EQU => &self.memory[base_ptr..][..6],
-            CGT => &self.memory[base_ptr..][..6],
-            CLT => &self.memory[base_ptr..][..6],
-            JMP => &self.memory[base_ptr..][..3],
-            JIE => &self.memory[base_ptr..][..5],
-            JNE => &self.memory[base_ptr..][..5],
-            PUT_I => &self.memory[base_ptr..][..4],
-            PUT_C => &self.memory[base_ptr..][..4],
-            IMZ => &self.memory[base_ptr..][..4],
-            HLT => &self.memory[base_ptr..][..1],
-            _ => panic!("[Halt]: Instruction resolution failed: Invalid opcode")
-        }.to_vec()
-    }
-    pub fn memory_fetch(&self, pointer_mode: u8, address_size: u8, input: u32) -> u64 {
-        match pointer_mode {
-            0 => {
-                let variable_bytes = u64_pad_le(&self.memory[input as usize..][..address_size as usize]);
-                u64::from_le_bytes(variable_bytes.try_into().expect("[Halt]: Memory fetch failed: Couldn't parse internal memory slice"))
-            }
-            1 => {
-                let pointer_bytes = &self.memory[input as usize..][..4];
-                let pointer = u32::from_le_bytes(pointer_bytes.try_into().expect("[Halt]: Memory fetch failed: Couldn't parse internal memory slice"));
-                let variable_bytes = u64_pad_le(&self.memory[pointer as usize..][..address_size as usize]);
-                u64::from_le_bytes(variable_bytes.try_into().expect("[Halt]: Memory fetch failed: Couldn't parse internal memory slice"))
-            }
-            2 => {
-                input as u64
-            }
-            _ => {
-                panic!("[Halt]: Memory fetch failed: Invalid pointer mode");
-            }
-        }
-    }
-    pub fn memory_write(&mut self, pointer_mode: u8, address_size: u8, input: u32, data: u64) {
-        match pointer_mode {
-            0 => {
-                self.memory[input as usize..][..address_size as usize].copy_from_slice(&data.to_le_bytes()[..address_size as usize]);
-            }
-            1 => {
-                let pointer_bytes = &self.memory[input as usize..][..4];
-                let pointer = u32::from_le_bytes(pointer_bytes.try_into().expect("[Halt]: Memory write failed: Couldn't parse internal memory slice"));
-                self.memory[pointer as usize..][..address_size as usize].copy_from_slice(&data.to_le_bytes()[..address_size as usize])
-            }
-            _ => {
-                panic!("[Halt]: Memory write failed: Invalid pointer mode");
-            }
-        }
-    }
-    /// Executes an instruction and returns the next program counter
-    pub fn execute_instruction(&mut self, instruction: &[u8]) -> usize {
-        // Decodes instruction
-        let opcode = instruction[0];
-        let pointer_mode = pointer_mode_decode(instruction);
-        let address_size = instruction.get(2);
-        match opcode {
-            MOV => {
-                let pointer_mode = pointer_mode.expect("[Halt]: Pointer mode decode failed: Function requires the pointer mode to be explicit");
-                let address_size = address_size.expect("[Halt]: Address size read failed: Function requires the address size to be explicit");
-                let args = [
-                    u32::from_le_bytes(instruction[3..7].try_into().expect("[Halt]: Argument parsing failed")),
-                    u32::from_le_bytes(instruction[7..11].try_into().expect("[Halt]: Argument parsing failed")),
-                ];
-                let value = self.memory_fetch(pointer_mode[0], *address_size, args[0]);
-                self.program_counter + instruction.len()
-            }
-            ADD => {
-
-                self.program_counter + instruction.len()
-            }
-            SUB => {
-
-                self.program_counter + instruction.len()
-            }
-            MUL => {
-
-                self.program_counter + instruction.len()
-            }
-            DIV => {
-
-                self.program_counter + instruction.len()
-            }
-            REM => {
-
-                self.program_counter + instruction.len()
-            }
-            EQU => {
-
-                self.program_counter + instruction.len()
-            }
-            CGT => {
-
-                self.program_counter + instruction.len()
-            }
-            CLT => {
-
-                self.program_counter + instruction.len()
-            }
-            JMP => {
-
-            }
-            JIE => {
-
-            }
-            JNE => {
-            }
-            PUT_=> {
-                self.program_counter + instruction.len()
-            }
-            PUT_=> {
-                self.program_counter + instruction.len()
-            }
-            IMZ => {
-                self.program_counter + instruction.len()
-            }
-            HLT => {
-                self.mode = TransientMode::HALTED;
-                self.program_counter + instruction.len()
-            }
-            _ => panic!("[Halt]: Instruction execution failed: Invalid opcode\n-> This is likely a bug in the virtual machine")
-        }
-    }
-}
-
-fn u64_pad_le(data: &[u8]) -> [u8; 8] {
-    let mut padded = [0u8; 8];
-    padded[..data.len()].copy_from_slice(data);
-    padded
+    exit(1);
 }
 
-fn pointer_mode_decode(instruction: &[u8]) -> Result<[u8; 4], ()> {
-    if instruction.len() < 2 {
-        Err(())
-    } else {
-        Ok([
-            instruction[1] & 0b00000011,
-            instruction[1] & 0b00001100 >> 2,
-            instruction[1] & 0b00110000 >> 4,
-            instruction[1] & 0b11000000 >> 6,
-        ])
+fn main() {
+    // The processor holds its transient memory inline, which doesn't fit on the stack of the main
+    // thread, so run it on a thread with room for a few copies of it
+    let vm = thread::Builder::new().stack_size(4 * TRANSIENT_MEM_MAX).spawn(run_vm);
+    if !matches!(vm.map(|x| x.join()), Ok(Ok(()))) {
+        exit(1);
     }
 }
 
-fn main() {
+fn run_vm() {
     // Verify input arguments
-    let args: Vec<String> = args().collect();
-    if args.len() != 2 {
-        panic!("Stop: Incorrect amount of arguments!");
+    // transientvm <image> [--profile] [--info] [--quiet | --verbose]
+    let mut profile = false;
+    let mut info = false;
+    let mut quiet = false;
+    let mut verbose = false;
+    let mut positional: Vec<String> = vec![];
+    for arg in args().skip(1) {
+        match &arg[..] {
+            "--profile" => profile = true,
+            "--info" => info = true,
+            "--quiet" => quiet = true,
+            "--verbose" => verbose = true,
+            _ => positional.push(arg),
+        }
     }
+    if positional.len() != 1 {
+        stop("Incorrect amount of arguments!");
+    }
+    if quiet && verbose {
+        stop("--quiet can't be combined with --verbose");
+    }
+    // Diagnostics go to stderr so that they don't mix with the output of the program
+    let log = |message: &str| {
+        if !quiet {
+            eprintln!("Info: {}", message);
+        }
+    };
 
     // Open file for reading
-    let mut input_file = match File::open(&args[1]) {
+    let mut input_file = match File::open(&positional[0]) {
         Ok(x) => x,
         Err(_) => {
-            panic!("Stop: Failed to open file");
+            stop("Failed to open file");
         }
     };
 
     // Read bytes into buffer
     let mut transient_image: Vec<u8> = vec![];
-    if let Err(_) = input_file.read_to_end(&mut transient_image) {
-        panic!("Stop: Failed to read file contents");
+    if input_file.read_to_end(&mut transient_image).is_err() {
+        stop("Failed to read file contents");
+    }
+    log("File read");
+
+    // Print the metadata the program was annotated with
+    if info {
+        match read_metadata(&transient_image) {
+            Ok(metadata) => {
+                for (key, value) in metadata {
+                    println!("Info: {}: {}", key, value);
+                }
+            }
+            Err(error) => halt(error),
+        }
     }
-    println!("Info: File read");
 
     // Initialize transient processor
     let mut transient_state = TransientState::<TRANSIENT_MEM_MAX>::new();
-    println!("Info: Transient processor initialized");
+    log("Transient processor initialized");
 
     // Copy over image at offset 0 (at the start)
-    transient_state.load_image(0, &transient_image);
-    println!("Info: Transient image loaded");
+    if let Err(error) = transient_state.load_image(0, &transient_image) {
+        halt(error);
+    }
+    log("Transient image loaded");
+
+    if profile {
+        transient_state.enable_profiling();
+    }
+    if verbose {
+        transient_state.enable_write_tracking();
+    }
 
     // Begin executing
-    transient_state.run(0);
+    if let Err(error) = transient_state.run(0) {
+        let debug_info = read_debug_info(&transient_image).unwrap_or_default();
+        halt_at(error, transient_state.program_counter, &debug_info);
+    }
 
-    println!("Info: End of program reached");
+    log("End of program reached");
+    if verbose {
+        log(&format!("Instructions executed: {}", transient_state.instructions_executed));
+        log(&format!("Final program counter: {:#06X}", transient_state.program_counter));
+        log(&format!("Bytes of memory written: {}", transient_state.touched_bytes().unwrap_or_default()));
+    }
+
+    if let Some(report) = transient_state.profile_report() {
+        println!("Profile:\n{}", report);
+    }
+
+    // Pass the exit code of HLTE on to the shell
+    if let Some(code) = transient_state.exit_code() {
+        exit(code);
+    }
 }
@@ -0,0 +1,2345 @@
+//! Compiler that transforms Transient-C into TransientIR. (TIR)
+//! Currently under development.
+
+/*
+    0x00: NOP does nothing
+    0x01: MOV byte from source1 into destination
+    0x02: ADD source1 and source2 and store result in destination
+    0x03: SUB source2 from source1 and store result in destination
+    0x04: MUL source1 and source2 and store result in destination
+    0x05: DIV source1 by source2 and store result in destination (truncated)
+    0x06: DIV source1 by source2 and store result in destination (rounded)
+    0x07: REM divides source1 by source2 and stores the remainder in destination
+    0x08: CGT compare if source1 is greater than source2, and if so, store 1 in destination
+    0x09: CLT compare if source1 is less than source2, and if so, store 1 in destination
+    0x0A: JMP stops current execution and jumps to code in source1
+    0x0B: JIE stops current execution and jumps to code in source1 ONLY IF source2 is non-zero
+    0x0C: JNE stops current execution and jumps to code in source1 ONLY IF source2 is zero
+    0x0D: PUT prints data at source1 to the screen (int)
+    0x0E: PUT prints data at source1 to the screen (char)
+    0x0F: IMZ gets the image size that was loaded to ROM and stores it in destination
+    0x10: EQU compare if source1 and source2 are equal, and if so, store 1 in destination
+    0x11: AND bitwise and source1 with source2 and store the result in destination
+    0x12: OR bitwise or source1 with source2 and store the result in destination
+    0x13: XOR bitwise xor source1 with source2 and store the result in destination
+    0x14: NOT invert every bit of source1 and store the result in destination
+    0x15: SHL shift source1 left by source2 bits and store the result in destination
+    0x16: SHR shift source1 right by source2 bits and store the result in destination
+    0x17: PUSH push source1 onto the stack
+    0x18: POP pop the top of the stack into destination
+    0x19: CALL pushes the address of the next instruction onto the stack and jumps to target
+    0x1A: RET pops an address from the stack and jumps to it
+    0x1B: CGE compare if source1 is greater than or equal to source2, and if so, store 1 in destination
+    0x1C: CLE compare if source1 is less than or equal to source2, and if so, store 1 in destination
+    0x1D: GET_I reads an integer from the input and stores it in destination
+    0x1E: GET_C reads a character from the input and stores it in destination
+    0x1F: PUT_S prints the null-terminated string starting at source1
+    0x20: MEMCPY copies a region of memory between the addresses held by source1 and source2
+    0x21: MEMSET fills the region of memory at the address held by source1 with a byte
+    0x22: SWAP exchanges the values of source1 and source2
+    0x23: BSWAP reverses the byte order of source1 and stores the result in destination
+    0x24: POPCNT counts the set bits of source1 and stores the count in destination
+    0x25: CLZ counts the leading zero bits of source1 and stores the count in destination
+    0x26: CTZ counts the trailing zero bits of source1 and stores the count in destination
+    0x27: ROTL rotate source1 left by source2 bits and store the result in destination
+    0x28: ROTR rotate source1 right by source2 bits and store the result in destination
+    0x29: INC adds 1 to source1 in place
+    0x2A: DEC subtracts 1 from source1 in place
+    0x2B: NEG negate source1 and store the result in destination
+    0x2C: ABS store the absolute value of signed source1 in destination
+    0x2D: ASSERT halts with an error if source1 is zero
+    0x2E: NASSERT halts with an error if source1 is non-zero
+    0x2F: TRAP pauses the processor if an instruction hook is registered
+    0x30: BOUNDS checks that source1 lies between source2 and source3 and stores the result in destination
+    0x31: SCGT compare if signed source1 is greater than signed source2, and if so, store 1 in destination
+    0x32: SCLT compare if signed source1 is less than signed source2, and if so, store 1 in destination
+    0x33: SEQU compare if signed source1 and signed source2 are equal, and if so, store 1 in destination
+    0x34: ADDO adds source1 and source2 into destination and flags whether the result wrapped
+    0x35: SUBO subtracts source2 from source1 into destination and flags whether the result wrapped
+    0x36: MULO multiplies source1 and source2 into destination and flags whether the result wrapped
+    0x37: SADD adds source1 and source2, saturating at the bounds of the size, and stores result in destination
+    0x38: SSUB subtracts source2 from source1, saturating at the bounds of the size, and stores result in destination
+    0x39: SMUL multiplies source1 and source2, saturating at the bounds of the size, and stores result in destination
+    0x3A: MIN stores the smaller of source1 and source2 in destination
+    0x3B: MAX stores the larger of source1 and source2 in destination
+    0x3C: MOD divides signed source1 by signed source2 and stores the non-negative remainder in destination
+    0x3D: IMM stores the value embedded in the instruction in destination
+    0x3E: RANDOM stores a pseudorandom number in destination
+    0x3F: CLOCK stores the nanoseconds elapsed since the processor was created in destination
+    0x40: PUT_H prints source1 in hexadecimal
+    0x41: STRLEN stores the length of the null-terminated string at source in destination
+    0x42: STRCMP compares the null-terminated strings at source1 and source2 and stores -1, 0, or 1 in destination
+    0x91: MEMMOVE copies a possibly overlapping region of memory
+    0x92: PRTMM prints the memory map of the loaded image
+    0x93: SYSCALL invokes a host operation from the fixed dispatch table
+    0x94: SAVEFP stores the frame pointer in destination
+    0x95: RESTFP loads the frame pointer from source1
+    0x96: SWITCH jumps to the target of the table entry matching source1
+    0x97: COSAVE stores the execution context in destination
+    0x98: CORESTORE loads the execution context from source1 and resumes it
+    0x99: PROFREGION marks the entry into a profiling region
+    0x9A: REPEAT executes the next instruction as many times as source1 holds
+    0x9B: SETPRED loads the condition register from source1
+    0x9C: CLRPRED sets the condition register to 1
+    0x9D: ADDMOD adds source1 and source2 modulo source3 and stores result in destination
+    0x9E: MULMOD multiplies source1 and source2 modulo source3 and stores result in destination
+    0x9F: MODPOW raises source1 to the power of source2 modulo source3 and stores result in destination
+    0xA0: INVMOD stores the inverse of source1 modulo source2 in destination, if it exists
+    0xA1: XORSHIFT64 advances the xorshift64 state in source1 and stores it in destination
+    0xA2: CHACHA20BLK computes the ChaCha20 block of the state at source1 into destination
+    0xA3: SHA256COMP applies the SHA-256 compression function of the block at source2 to the state at source1
+    0xA4: POPSTACK discards as many bytes from the top of the stack as source1 holds
+    0xA5: PEEK copies the top of the stack into destination without popping it
+    0xA6: PUSHPC pushes the address of the next instruction onto the stack
+    0xA7: POPPC pops an address from the stack and jumps to it
+    0xA8: VCMPEQ8 compares two byte arrays for equality element-wise
+    0xA9: VCMPGT8 compares if the bytes of one array are greater than another element-wise
+    0xAA: DOTPROD computes the dot product of two byte arrays and stores it in destination
+    0xAB: TLOOKUP copies the table element at the index in source1 into destination
+    0xAC: SETOUT redirects output to the device with the id in source1
+    0xAD: SETIN redirects input to the device with the id in source1
+    0xAE: BLOOPN runs the loop body at source2 the number of times in source1
+    0xAF: LEND ends a BLOOPN loop body
+    0xB0: RFIND stores the offset of the last byte equal to source3 in the region at source1 into destination
+    0xB1: SPLITB unpacks the value in source1 into consecutive bytes starting at destination
+    0xB2: JOINB packs the consecutive bytes starting at source1 into destination
+    0xB3: INTERLEAVE alternates the bytes of the arrays at source1 and source2 into destination
+    0xB4: DEINTERLEAVE separates the alternating bytes of the array at source1 into two arrays
+    0xB5: RLEENC run-length encodes the region at source1 into (count, value) byte pairs
+    0xB6: RLEDEC expands the (count, value) byte pairs at source1 into the region at destination
+    0xB7: DELTAENC writes the byte differences of the region at source1 into destination
+    0xB8: DELTADEC reconstructs the bytes of the difference sequence at source1 into destination
+    0xB9: NIBPK packs pairs of 4-bit values from the array at source1 into single bytes at destination
+    0xBA: NIBUPK unpacks the 4-bit values packed at source1 into separate bytes at destination
+    0xBB: FIBSTEP advances the Fibonacci pair in source1 and source2 by one step
+    0xBC: BSEARCH binary searches the sorted byte array at source1 for source3 and stores the index in destination
+    0xBD: QSORT sorts the array of elements at source1 in lexicographic byte order
+    0xBE: UNIQSORT sorts and deduplicates the byte array at source1 in place
+    0xBF: PCTILE stores the value at the percentile in source3 of the byte array at source1 into destination
+    0xC0: HISTO counts the occurrences of every byte value in the region at source1 into destination
+    0xC1: WSAMPLE stores the index of a random sample from the weights at source1 into destination
+    0xC2: BITMATMUL multiplies the bit matrices at source1 and source2 over GF(2) into destination
+    0xC3: POPCNTA counts the set bits in the region at source1 into destination
+    0xC4: DITHER dithers the grayscale image at source1 to black and white into destination
+    0xFE: HLTE halts execution and stops processor with the exit code in source1
+    0xFF: HLT halts execution and stops processor
+*/
+
+use crate::error::{CompileError, TransientError};
+use crate::image::{encode_debug_info, encode_metadata, encode_symbols, file_hash, write_image, Metadata, DEBUG_MARKER, HEADER_SIZE, METADATA_MARKER, SYMBOL_MARKER};
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+
+/// Set in the size byte of a jump to execute the following instruction before the jump takes effect
+const DELAY_SLOT_FLAG: usize = 0x40;
+/// Set in the size byte of an instruction to only execute it if the condition register is non-zero
+const PREDICATE_FLAG: u8 = 0x80;
+
+/// A single instruction of the abstract syntax tree, with its operand size and resolved addresses
+#[derive(Debug, Hash, Eq, PartialEq)]
+pub enum Operation {
+    Nop(),
+    Mov(usize, usize, usize),
+    Add(usize, usize, usize, usize),
+    Sub(usize, usize, usize, usize),
+    Mul(usize, usize, usize, usize),
+    DivT(usize, usize, usize, usize),
+    DivR(usize, usize, usize, usize),
+    Rem(usize, usize, usize, usize),
+    Cgt(usize, usize, usize, usize),
+    Clt(usize, usize, usize, usize),
+    Jmp(usize),
+    Jie(usize, usize, usize),
+    Jne(usize, usize, usize),
+    JmpD(usize),
+    JieD(usize, usize, usize),
+    JneD(usize, usize, usize),
+    PutI(usize, usize),
+    PutC(usize, usize),
+    Imz(usize, usize),
+    Equ(usize, usize, usize, usize),
+    And(usize, usize, usize, usize),
+    Or(usize, usize, usize, usize),
+    Xor(usize, usize, usize, usize),
+    Not(usize, usize, usize),
+    Shl(usize, usize, usize, usize),
+    Shr(usize, usize, usize, usize),
+    Push(usize, usize),
+    Pop(usize, usize),
+    Call(usize),
+    Ret(),
+    Cge(usize, usize, usize, usize),
+    Cle(usize, usize, usize, usize),
+    GetI(usize, usize),
+    GetC(usize, usize),
+    PutS(usize, usize),
+    Memcpy(usize, usize, usize),
+    Memset(usize, usize, usize),
+    Swap(usize, usize, usize),
+    Bswap(usize, usize, usize),
+    Popcnt(usize, usize, usize),
+    Clz(usize, usize, usize),
+    Ctz(usize, usize, usize),
+    Rotl(usize, usize, usize, usize),
+    Rotr(usize, usize, usize, usize),
+    Inc(usize, usize),
+    Dec(usize, usize),
+    Neg(usize, usize, usize),
+    Abs(usize, usize, usize),
+    Assert(usize, usize),
+    Nassert(usize, usize),
+    Trap(),
+    Bounds(usize, usize, usize, usize, usize),
+    Scgt(usize, usize, usize, usize),
+    Sclt(usize, usize, usize, usize),
+    Sequ(usize, usize, usize, usize),
+    Addo(usize, usize, usize, usize, usize),
+    Subo(usize, usize, usize, usize, usize),
+    Mulo(usize, usize, usize, usize, usize),
+    Sadd(usize, usize, usize, usize),
+    Ssub(usize, usize, usize, usize),
+    Smul(usize, usize, usize, usize),
+    Min(usize, usize, usize, usize),
+    Max(usize, usize, usize, usize),
+    Mod(usize, usize, usize, usize),
+    Imm(usize, usize, usize),
+    Rand(usize, usize),
+    Clock(usize, usize),
+    PutH(usize, usize),
+    Strlen(usize, usize),
+    Strcmp(usize, usize, usize),
+    Memmove(usize, usize, usize),
+    Prtmm(),
+    Syscall(usize),
+    Savefp(usize),
+    Restfp(usize),
+    Switch(usize, usize, usize, usize),
+    Cosave(usize),
+    Corestore(usize),
+    Profregion(usize),
+    Repeat(usize),
+    Setpred(usize),
+    Clrpred(),
+    Addmod(usize, usize, usize, usize, usize),
+    Mulmod(usize, usize, usize, usize, usize),
+    Modpow(usize, usize, usize, usize, usize),
+    Invmod(usize, usize, usize, usize, usize),
+    Xorshift64(usize, usize),
+    Chacha20blk(usize, usize),
+    Sha256comp(usize, usize),
+    Popstack(usize),
+    Peek(usize, usize),
+    Pushpc(),
+    Poppc(),
+    Vcmpeq8(usize, usize, usize, usize),
+    Vcmpgt8(usize, usize, usize, usize),
+    Dotprod(usize, usize, usize, usize),
+    Tlookup(usize, usize, usize, usize),
+    Setout(usize),
+    Setin(usize),
+    Bloopn(usize, usize),
+    Lend(),
+    Rfind(usize, usize, usize, usize),
+    Splitb(usize, usize, usize),
+    Joinb(usize, usize, usize),
+    Interleave(usize, usize, usize, usize),
+    Deinterleave(usize, usize, usize, usize),
+    Rleenc(usize, usize, usize, usize, usize),
+    Rledec(usize, usize, usize, usize, usize),
+    Deltaenc(usize, usize, usize),
+    Deltadec(usize, usize, usize),
+    Nibpk(usize, usize, usize),
+    Nibupk(usize, usize, usize),
+    Fibstep(usize, usize),
+    Bsearch(usize, usize, usize, usize),
+    Qsort(usize, usize, usize, usize),
+    Uniqsort(usize, usize, usize),
+    Pctile(usize, usize, usize, usize),
+    Histo(usize, usize, usize),
+    Wsample(usize, usize, usize, usize),
+    Bitmatmul(usize, usize, usize, usize, usize, usize),
+    Popcnta(usize, usize, usize),
+    Dither(usize, usize, usize, usize),
+    Hlte(usize, usize),
+    Hlt(),
+    Predicated(Box<Operation>),
+}
+
+/// Variable name to address, initial value, and size in bytes
+pub type MemoryMap = HashMap<String, (usize, Initializer, usize)>;
+
+/// Initial value of a variable in the data section
+#[derive(Debug)]
+pub enum Initializer {
+    Integer(u64),
+    String(Vec<u8>),
+}
+
+impl std::fmt::Display for Initializer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Initializer::Integer(value) => write!(f, "{}", value),
+            Initializer::String(bytes) => write!(f, "{:?}", String::from_utf8_lossy(bytes)),
+        }
+    }
+}
+
+/// Operations with a fixed layout that are written without a size suffix
+const UNSIZED_OPERATIONS: &[&str] = &["nop", "call", "ret", "memcpy", "memset", "trap", "strlen", "strcmp", "memmove", "prtmm", "syscall", "savefp", "restfp", "switch", "cosave", "corestore", "profr", "rep", "setpred", "clrpred", "xorsh64", "chacha20", "sha256", "popstk", "pushpc", "poppc", "vcmpeq8", "vcmpgt8", "dotprod", "tlookup", "setout", "setin", "bloopn", "lend", "rfind", "interleave", "deinterleave", "rleenc", "rledec", "deltaenc", "deltadec", "nibpk", "nibupk", "fibstep", "bsearch", "qsort", "uniqsort", "pctile", "histo", "wsample", "bitmatmul", "popcnta", "dither"];
+
+fn resolve_operation_opcode(operation: &Operation) -> u8 {
+    match operation {
+        Operation::Nop(..) => 0x00,
+        Operation::Mov(..) => 0x01,
+        Operation::Add(..) => 0x02,
+        Operation::Sub(..) => 0x03,
+        Operation::Mul(..) => 0x04,
+        Operation::DivT(..) => 0x05,
+        Operation::DivR(..) => 0x06,
+        Operation::Rem(..) => 0x07,
+        Operation::Cgt(..) => 0x08,
+        Operation::Clt(..) => 0x09,
+        Operation::Jmp(..) => 0x0A,
+        Operation::Jie(..) => 0x0B,
+        Operation::Jne(..) => 0x0C,
+        Operation::JmpD(..) => 0x0A,
+        Operation::JieD(..) => 0x0B,
+        Operation::JneD(..) => 0x0C,
+        Operation::PutI(..) => 0x0D,
+        Operation::PutC(..) => 0x0E,
+        Operation::Imz(..) => 0x0F,
+        Operation::Equ(..) => 0x10,
+        Operation::And(..) => 0x11,
+        Operation::Or(..) => 0x12,
+        Operation::Xor(..) => 0x13,
+        Operation::Not(..) => 0x14,
+        Operation::Shl(..) => 0x15,
+        Operation::Shr(..) => 0x16,
+        Operation::Push(..) => 0x17,
+        Operation::Pop(..) => 0x18,
+        Operation::Call(..) => 0x19,
+        Operation::Ret(..) => 0x1A,
+        Operation::Cge(..) => 0x1B,
+        Operation::Cle(..) => 0x1C,
+        Operation::GetI(..) => 0x1D,
+        Operation::GetC(..) => 0x1E,
+        Operation::PutS(..) => 0x1F,
+        Operation::Memcpy(..) => 0x20,
+        Operation::Memset(..) => 0x21,
+        Operation::Swap(..) => 0x22,
+        Operation::Bswap(..) => 0x23,
+        Operation::Popcnt(..) => 0x24,
+        Operation::Clz(..) => 0x25,
+        Operation::Ctz(..) => 0x26,
+        Operation::Rotl(..) => 0x27,
+        Operation::Rotr(..) => 0x28,
+        Operation::Inc(..) => 0x29,
+        Operation::Dec(..) => 0x2A,
+        Operation::Neg(..) => 0x2B,
+        Operation::Abs(..) => 0x2C,
+        Operation::Assert(..) => 0x2D,
+        Operation::Nassert(..) => 0x2E,
+        Operation::Trap(..) => 0x2F,
+        Operation::Bounds(..) => 0x30,
+        Operation::Scgt(..) => 0x31,
+        Operation::Sclt(..) => 0x32,
+        Operation::Sequ(..) => 0x33,
+        Operation::Addo(..) => 0x34,
+        Operation::Subo(..) => 0x35,
+        Operation::Mulo(..) => 0x36,
+        Operation::Sadd(..) => 0x37,
+        Operation::Ssub(..) => 0x38,
+        Operation::Smul(..) => 0x39,
+        Operation::Min(..) => 0x3A,
+        Operation::Max(..) => 0x3B,
+        Operation::Mod(..) => 0x3C,
+        Operation::Imm(..) => 0x3D,
+        Operation::Rand(..) => 0x3E,
+        Operation::Clock(..) => 0x3F,
+        Operation::PutH(..) => 0x40,
+        Operation::Strlen(..) => 0x41,
+        Operation::Strcmp(..) => 0x42,
+        Operation::Memmove(..) => 0x91,
+        Operation::Prtmm(..) => 0x92,
+        Operation::Syscall(..) => 0x93,
+        Operation::Savefp(..) => 0x94,
+        Operation::Restfp(..) => 0x95,
+        Operation::Switch(..) => 0x96,
+        Operation::Cosave(..) => 0x97,
+        Operation::Corestore(..) => 0x98,
+        Operation::Profregion(..) => 0x99,
+        Operation::Repeat(..) => 0x9A,
+        Operation::Setpred(..) => 0x9B,
+        Operation::Clrpred(..) => 0x9C,
+        Operation::Addmod(..) => 0x9D,
+        Operation::Mulmod(..) => 0x9E,
+        Operation::Modpow(..) => 0x9F,
+        Operation::Invmod(..) => 0xA0,
+        Operation::Xorshift64(..) => 0xA1,
+        Operation::Chacha20blk(..) => 0xA2,
+        Operation::Sha256comp(..) => 0xA3,
+        Operation::Popstack(..) => 0xA4,
+        Operation::Peek(..) => 0xA5,
+        Operation::Pushpc(..) => 0xA6,
+        Operation::Poppc(..) => 0xA7,
+        Operation::Vcmpeq8(..) => 0xA8,
+        Operation::Vcmpgt8(..) => 0xA9,
+        Operation::Dotprod(..) => 0xAA,
+        Operation::Tlookup(..) => 0xAB,
+        Operation::Setout(..) => 0xAC,
+        Operation::Setin(..) => 0xAD,
+        Operation::Bloopn(..) => 0xAE,
+        Operation::Lend(..) => 0xAF,
+        Operation::Rfind(..) => 0xB0,
+        Operation::Splitb(..) => 0xB1,
+        Operation::Joinb(..) => 0xB2,
+        Operation::Interleave(..) => 0xB3,
+        Operation::Deinterleave(..) => 0xB4,
+        Operation::Rleenc(..) => 0xB5,
+        Operation::Rledec(..) => 0xB6,
+        Operation::Deltaenc(..) => 0xB7,
+        Operation::Deltadec(..) => 0xB8,
+        Operation::Nibpk(..) => 0xB9,
+        Operation::Nibupk(..) => 0xBA,
+        Operation::Fibstep(..) => 0xBB,
+        Operation::Bsearch(..) => 0xBC,
+        Operation::Qsort(..) => 0xBD,
+        Operation::Uniqsort(..) => 0xBE,
+        Operation::Pctile(..) => 0xBF,
+        Operation::Histo(..) => 0xC0,
+        Operation::Wsample(..) => 0xC1,
+        Operation::Bitmatmul(..) => 0xC2,
+        Operation::Popcnta(..) => 0xC3,
+        Operation::Dither(..) => 0xC4,
+        Operation::Hlte(..) => 0xFE,
+        Operation::Hlt(..) => 0xFF,
+        Operation::Predicated(operation) => resolve_operation_opcode(operation),
+    }
+}
+
+/// Returns the length in bytes of the encoded instruction for a mnemonic
+fn resolve_operation_length(mnemonic: &str) -> usize {
+    match mnemonic {
+        "nop" => 1,
+        "call" => 5,
+        "ret" => 1,
+        "memcpy" => 13,
+        "memset" => 13,
+        "trap" => 1,
+        "bounds" => 18,
+        "addo" => 18,
+        "subo" => 18,
+        "mulo" => 18,
+        "strlen" => 9,
+        "strcmp" => 13,
+        "memmove" => 13,
+        "prtmm" => 1,
+        "syscall" => 5,
+        "savefp" => 5,
+        "restfp" => 5,
+        "switch" => 17,
+        "cosave" => 5,
+        "corestore" => 5,
+        "profr" => 5,
+        "rep" => 5,
+        "setpred" => 5,
+        "clrpred" => 1,
+        "addmod" => 18,
+        "mulmod" => 18,
+        "modpow" => 18,
+        "invmod" => 18,
+        "xorsh64" => 9,
+        "chacha20" => 9,
+        "sha256" => 9,
+        "popstk" => 5,
+        "peek" => 6,
+        "pushpc" => 1,
+        "poppc" => 1,
+        "vcmpeq8" => 17,
+        "vcmpgt8" => 17,
+        "dotprod" => 17,
+        "tlookup" => 14,
+        "setout" => 5,
+        "setin" => 5,
+        "bloopn" => 9,
+        "lend" => 1,
+        "rfind" => 17,
+        "splitb" => 10,
+        "joinb" => 10,
+        "interleave" => 17,
+        "deinterleave" => 17,
+        "rleenc" => 21,
+        "rledec" => 21,
+        "deltaenc" => 13,
+        "deltadec" => 13,
+        "nibpk" => 13,
+        "nibupk" => 13,
+        "fibstep" => 9,
+        "bsearch" => 17,
+        "qsort" => 17,
+        "uniqsort" => 13,
+        "pctile" => 17,
+        "histo" => 13,
+        "wsample" => 17,
+        "bitmatmul" => 25,
+        "popcnta" => 13,
+        "dither" => 17,
+        _ => 14,
+    }
+}
+
+/// Splits an operation token such as `add64` into its mnemonic and size in bytes. Unsized
+/// operations resolve to a size of 0.
+fn split_operation(token: &str, line: &str, line_number: usize) -> Result<(String, usize), CompileError> {
+    if UNSIZED_OPERATIONS.contains(&token) {
+        return Ok((token.to_owned(), 0));
+    }
+    // Extract 'add' from 'add64'
+    let mnemonic: String = token.chars().filter(|x| x.is_alphabetic()).collect::<String>();
+    let size: usize = token.chars().filter(|x|{x.is_numeric()}).collect::<String>().parse::<usize>().map_err(|_| { halt_compilation("[E003] Failed to parse size: Did you remember to specify the size of the operation?", line, line_number)})? / 8;
+    Ok((mnemonic, size))
+}
+
+/// Returns whether a line of IR is a variable declaration such as `set64 $x 5`
+fn is_declaration(line: &str) -> bool {
+    line.strip_prefix("set")
+        .is_some_and(|x| x.starts_with(|c: char| c.is_ascii_digit()))
+        || is_string_declaration(line)
+}
+
+/// Returns whether a line declares a string, e.g. `setstr $message "hello\n"`
+fn is_string_declaration(line: &str) -> bool {
+    line.starts_with("setstr ")
+}
+
+/// Resolves the character after a backslash in a string or character literal
+fn escape_byte(c: char) -> Option<u8> {
+    match c {
+        'n' => Some(b'\n'),
+        't' => Some(b'\t'),
+        '\\' => Some(b'\\'),
+        '"' => Some(b'"'),
+        '\'' => Some(b'\''),
+        _ => None,
+    }
+}
+
+/// Parses a quoted string literal into its bytes followed by a null terminator, resolving the
+/// `\n`, `\t`, `\\`, `\"`, and `\'` escapes
+fn parse_string_literal(literal: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut chars = literal.strip_prefix('"')?.strip_suffix('"')?.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => bytes.push(escape_byte(chars.next()?)?),
+            c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    bytes.push(0x00);
+    Some(bytes)
+}
+
+/// Parses the value of an integer declaration. Besides decimal, values can be hexadecimal
+/// (`0xFF`), binary (`0b1010`), or a single-quoted character (`'a'`, `'\n'`)
+fn parse_integer_literal(literal: &str) -> Option<u64> {
+    if let Some(digits) = literal.strip_prefix("0x") {
+        u64::from_str_radix(digits, 16).ok()
+    } else if let Some(digits) = literal.strip_prefix("0b") {
+        u64::from_str_radix(digits, 2).ok()
+    } else if let Some(character) = literal.strip_prefix('\'') {
+        let mut chars = character.strip_suffix('\'')?.chars();
+        let value = match chars.next()? {
+            '\\' => escape_byte(chars.next()?)? as u64,
+            c => c as u64,
+        };
+        chars.next().is_none().then_some(value)
+    } else {
+        literal.parse::<u64>().ok()
+    }
+}
+
+/// Returns the length in bytes of the encoded instruction on a line of IR
+fn resolve_line_length(line: &str, line_number: usize) -> Result<usize, CompileError> {
+    let token = line.split(" ").next().unwrap_or_default().trim_start_matches("?");
+    match split_operation(token, line, line_number)? {
+        // The value of an IMM is embedded in the instruction, between the size byte and the address
+        (mnemonic, size) if mnemonic == "imm" => Ok(2 + size + 4),
+        (mnemonic, _) => Ok(resolve_operation_length(&mnemonic)),
+    }
+}
+
+/// Lowers a `mov` of an intermediate into an `imm` of its value, so that `mov64 !64_15 $result`
+/// becomes `imm64 15 $result`. This saves the variable the intermediate would otherwise need. Only
+/// `mov` is lowered, and only when the intermediate has the operation's size. This runs on the
+/// source text because an `imm` is longer than a `mov`, which shifts every address after it.
+pub fn lower_immediates(source_code: Vec<(usize, String)>) -> Vec<(usize, String)> {
+    source_code
+        .into_iter()
+        .map(|(line_number, line)| match lower_immediate(&line) {
+            Some(lowered) => (line_number, lowered),
+            None => (line_number, line),
+        })
+        .collect()
+}
+
+/// Returns the `imm` that replaces a line, if it is a `mov` of an intermediate
+fn lower_immediate(line: &str) -> Option<String> {
+    let [operation, src, dest] = line.split(" ").collect::<Vec<&str>>()[..] else {
+        return None;
+    };
+    let bits = operation.strip_prefix("mov")?;
+    let mask = match bits {
+        "8" | "16" | "32" | "64" => u64::MAX >> (64 - bits.parse::<u32>().ok()?),
+        _ => return None,
+    };
+    let (size, value) = src.strip_prefix("!")?.split_once("_")?;
+    (size == bits).then_some(format!("imm{bits} {} {dest}", value.parse::<u64>().ok()? & mask))
+}
+
+/// Folds `add`, `sub`, `mul`, and `divt` operations whose operands are both intermediates into a
+/// `mov` from an intermediate holding the precomputed result, so that `add64 !64_10 !64_5 $result`
+/// computes the same as `mov64 !64_15 $result`. Only intermediates of the operation's size are
+/// folded, and divisions by zero are left to the processor. A `mov` is as long as the operation it
+/// replaces, so no addresses shift. The intermediate holding the result is added to the memory map
+/// if the program doesn't already use it. Adding or subtracting 1 in place, as in
+/// `add64 $i !64_1 $i`, becomes an `inc` or `dec`.
+pub fn optimize_constants(abstract_syntax_tree: Vec<Operation>, memory_map: &mut MemoryMap) -> Vec<Operation> {
+    abstract_syntax_tree
+        .into_iter()
+        .map(|operation| fold_constant_operation(operation, memory_map))
+        .collect()
+}
+
+/// Returns the operation with its arithmetic on two intermediates replaced by a `mov`, or its in
+/// place addition or subtraction of 1 replaced by an `inc` or `dec`
+fn fold_constant_operation(operation: Operation, memory_map: &mut MemoryMap) -> Operation {
+    let (size, src1, src2, dest) = match operation {
+        Operation::Predicated(operation) => {
+            return Operation::Predicated(Box::new(fold_constant_operation(*operation, memory_map)))
+        }
+        Operation::Add(size, src1, src2, dest)
+        | Operation::Sub(size, src1, src2, dest)
+        | Operation::Mul(size, src1, src2, dest)
+        | Operation::DivT(size, src1, src2, dest) if matches!(size, 1 | 2 | 4 | 8) => (size, src1, src2, dest),
+        operation => return operation,
+    };
+    let (a, b) = (intermediate_at(memory_map, src1, size), intermediate_at(memory_map, src2, size));
+    match (&operation, a, b) {
+        (Operation::Add(..), None, Some(1)) if src1 == dest => return Operation::Inc(size, dest),
+        (Operation::Add(..), Some(1), None) if src2 == dest => return Operation::Inc(size, dest),
+        (Operation::Sub(..), None, Some(1)) if src1 == dest => return Operation::Dec(size, dest),
+        _ => {}
+    }
+    let (Some(a), Some(b)) = (a, b) else {
+        return operation;
+    };
+    let result = match operation {
+        Operation::Add(..) => a.wrapping_add(b),
+        Operation::Sub(..) => a.wrapping_sub(b),
+        Operation::Mul(..) => a.wrapping_mul(b),
+        _ => match a.checked_div(b) {
+            Some(result) => result,
+            None => return operation,
+        },
+    } & (u64::MAX >> (64 - size * 8));
+    let name = intermediate_hash(&format!("!{}_{}", size * 8, result)).to_string();
+    let end = memory_map.values().map(|(address, _, size)| address + size).max().unwrap_or_default();
+    let (address, _, _) = memory_map.entry(name).or_insert((end, Initializer::Integer(result), size));
+    Operation::Mov(size, *address, dest)
+}
+
+/// Returns the value of the intermediate of the given size at an address, if there is one
+fn intermediate_at(memory_map: &MemoryMap, address: usize, size: usize) -> Option<u64> {
+    memory_map.iter().find_map(|(name, (variable_address, initializer, variable_size))| match initializer {
+        Initializer::Integer(value)
+            if *variable_address == address
+                && *variable_size == size
+                && *name == intermediate_hash(&format!("!{}_{}", size * 8, value)).to_string() =>
+        {
+            Some(*value)
+        }
+        _ => None,
+    })
+}
+
+/// Returns the hash that names the variable of an intermediate such as `!64_10`
+fn intermediate_hash(token: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Parses an annotation such as `@author "alice"`, without the `@`, into its key and value
+fn parse_annotation(annotation: &str, line: &str, line_number: usize) -> Result<(String, String), CompileError> {
+    let invalid = || halt_compilation("[E019] Invalid annotation: Annotations look like @name \"my program\", with at most 255 bytes in the name and the value", line, line_number);
+    let (key, value) = annotation.split_once(" ").ok_or_else(invalid)?;
+    let mut value = parse_string_literal(value).ok_or_else(invalid)?;
+    value.pop(); // Null terminator
+    if key.is_empty() || key.len() > 255 || value.len() > 255 {
+        return Err(invalid());
+    }
+    Ok((key.to_owned(), String::from_utf8(value).map_err(|_| invalid())?))
+}
+
+/// Expands the arguments of a `#!fatal "message"` macro into the lines that print the message and
+/// halt. If an exit code follows the message, as in `#!fatal "out of memory\n" 2`, the program halts
+/// with it. `index` makes the names of the generated variables unique.
+fn expand_fatal_macro(arguments: &str, index: usize, line: &str, line_number: usize) -> Result<Vec<String>, CompileError> {
+    let (message, code) = match arguments.rsplit_once(" ") {
+        Some((message, code)) if !arguments.ends_with('"') => (message, Some(code)),
+        _ => (arguments, None),
+    };
+    if parse_string_literal(message).is_none() {
+        return Err(halt_compilation("[E016] Failed to parse string: Strings must be quoted and may only use the \\n, \\t, \\\\, \\\", and \\' escapes", line, line_number));
+    }
+    let mut hasher = DefaultHasher::new();
+    (index, line).hash(&mut hasher);
+    let id = hasher.finish();
+    let mut lines = vec![
+        format!("setstr $__fatal_msg_{id} {message}"),
+        format!("puts8 $__fatal_msg_{id}"),
+    ];
+    match code {
+        Some(code) => {
+            lines.push(format!("set8 $__fatal_code_{id} {code}"));
+            lines.push(format!("hlte8 $__fatal_code_{id}"));
+        }
+        None => lines.push("hlt64".to_owned()),
+    }
+    Ok(lines)
+}
+
+/// Turns source code, given as lines with their 1-based line number, into an abstract syntax tree,
+/// the memory map of its variables, the metadata from its annotations, and the line number of
+/// every operation
+pub fn preprocess_source_code(
+    source_code: Vec<(usize, String)>,
+) -> Result<(Vec<Operation>, MemoryMap, Metadata, Vec<usize>), CompileError> {
+    let mut source_code = source_code;
+
+    // Pass 1
+    // Remove all comments, extract annotations, and expand #!fatal macros
+    source_code.retain(|(_, x)| !x.starts_with("//"));
+    let mut metadata: Metadata = vec![];
+    let mut expanded_source_code = Vec::with_capacity(source_code.len());
+    for (index, (line_number, line)) in source_code.into_iter().enumerate() {
+        if let Some(annotation) = line.strip_prefix("@") {
+            metadata.push(parse_annotation(annotation, &line, line_number)?);
+        } else if let Some(arguments) = line.strip_prefix("#!fatal ") {
+            for expanded_line in expand_fatal_macro(arguments, index, &line, line_number)? {
+                expanded_source_code.push((line_number, expanded_line));
+            }
+        } else {
+            expanded_source_code.push((line_number, line));
+        }
+    }
+    source_code = expanded_source_code;
+
+    // Pass 2
+    // Calculate all intermediates
+    let mut intermediates: HashMap<u64, (usize, usize, usize)> = HashMap::new();
+    for (line_number, line) in source_code.iter() {
+        if is_string_declaration(line) {
+            continue;
+        }
+        let line_tokens: Vec<String> = line.split(" ").map(|x| x.to_owned()).collect();
+        for token in line_tokens {
+            if !token.starts_with("!") {
+                continue;
+            }
+            let intermediate_parts: Vec<String> = token.split("_").map(|x| x.to_owned()).collect();
+            if intermediate_parts.len() != 2 {
+                return Err(halt_compilation(
+                    "[E011] Intermediate syntax incorrect. Did you remember to specify the size?",
+                    line,
+                    *line_number,
+                ));
+            }
+            let size = intermediate_parts[0][1..].parse::<usize>().map_err(|_| { halt_compilation("[E003] Failed to parse size: Did you remember to specify the size of the operation?", line, *line_number)})?;
+            let value = intermediate_parts[1].parse::<usize>().map_err(|_| {
+                halt_compilation(
+                    "[E012] Failed to parse intermediate value: Only integers are allowed",
+                    line,
+                    *line_number,
+                )
+            })?;
+            let hash = intermediate_hash(&token);
+            if intermediates.contains_key(&hash) {
+                continue;
+            }
+            intermediates.insert(hash, (value, size, *line_number));
+        }
+    }
+    // Pass 3
+    // Insert new intermediate variable declarations
+    for (hash, (value, size, line_number)) in intermediates.iter() {
+        source_code.insert(0, (*line_number, format!("set{size} ${hash} {value}")));
+        for (_, line) in source_code.iter_mut().filter(|(_, line)| !is_string_declaration(line)) {
+            *line = line.replace(&format!("!{size}_{value}"), &format!("${hash}"));
+        }
+    }
+
+    // Pass 4
+    // Count IR bytes
+    let mut ir_size_bytes = 0usize;
+    for (line_number, line) in &source_code {
+        // Check if it's actual IR
+        if !line.is_empty()
+            && !line.starts_with("#")
+            && !line.starts_with("//")
+            && !is_declaration(line)
+        {
+            ir_size_bytes += resolve_line_length(line, *line_number)?;
+        }
+    }
+
+    // Pass 5
+    // Build hashmap of variables to memory
+    let mut memory_map: MemoryMap = HashMap::new(); // Address, value,
+                                                                               // size
+    let mut memory_offset = 0usize;
+    for (line_number, line) in &source_code {
+        // Skip if not declaration
+        if !is_declaration(line) {
+            continue;
+        }
+        // set{bits} $variable value, or setstr $variable "string" where the string may contain spaces,
+        // as may a character value (' ')
+        let line_tokens: Vec<String> = if is_string_declaration(line) || line.ends_with("' '") {
+            line.splitn(3, " ").map(|x| x.to_owned()).collect()
+        } else {
+            line.split(" ").map(|x| x.to_owned()).collect()
+        };
+        if line_tokens.len() != 3 {
+            return Err(halt_compilation(
+                "[E001] Invalid set syntax: Did you remember to initialize the variable?",
+                line,
+                *line_number,
+            ));
+        }
+        if !line_tokens[1].starts_with("$") {
+            return Err(halt_compilation(
+                "[E002] Invalid variable: Did you remember to preface it with a dollar sign? ($)",
+                line,
+                *line_number,
+            ));
+        }
+        // Check if variable exists
+        if memory_map.contains_key(&line_tokens[1][1..]) {
+            return Err(halt_compilation(
+                "[E010] Variable memory collision: Did you initialize the same variable twice?",
+                line,
+                *line_number,
+            ));
+        }
+        let (value, size) = if is_string_declaration(line) {
+            let bytes = parse_string_literal(&line_tokens[2]).ok_or_else(|| {
+                halt_compilation("[E016] Failed to parse string: Strings must be quoted and may only use the \\n, \\t, \\\\, \\\", and \\' escapes", line, *line_number)
+            })?;
+            let size = bytes.len();
+            (Initializer::String(bytes), size)
+        } else {
+            let size = match line_tokens[0][3..].parse::<usize>() {
+                Ok(x) => x / 8,
+                Err(..) => return Err(halt_compilation("[E003] Failed to parse size: Did you remember to specify the size of the operation?", line, *line_number)),
+            };
+            if size > 8 {
+                return Err(halt_compilation("[E017] Variable too large: Variables can be at most 64 bits", line, *line_number));
+            }
+            let literal = &line_tokens[2];
+            let value = match parse_integer_literal(literal) {
+                Some(x) => x,
+                None if literal.starts_with("0x") || literal.starts_with("0b") || literal.starts_with('\'') => {
+                    return Err(halt_compilation(
+                        "[E013] Malformed literal: Hexadecimal, binary, and character values look like 0xFF, 0b1010, and 'a'",
+                        line,
+                        *line_number,
+                    ))
+                }
+                None => return Err(halt_compilation(
+                    "[E004] Failed to parse value: Only integer values are allowed",
+                    line,
+                    *line_number,
+                )),
+            };
+            (Initializer::Integer(value), size)
+        };
+
+        memory_map.insert(
+            line_tokens[1][1..].to_string(),
+            (ir_size_bytes + memory_offset, value, size),
+        );
+        memory_offset += size
+    }
+
+    // Pass 6
+    // Erase sets, and empty lines
+    source_code.retain(|(_, line)| !line.is_empty() && !is_declaration(line));
+
+    // Pass 7
+    // Record the address of every tag in a single scan, so that operations can refer to tags
+    // further down, then remove the tags
+    let mut jump_addresses: HashMap<String, usize> = HashMap::new();
+    let mut address = 0usize;
+    for (line_number, line) in &source_code {
+        match line.strip_prefix("#") {
+            Some(tag) => {
+                jump_addresses.insert(tag.to_owned(), address);
+            }
+            None => address += resolve_line_length(line, *line_number)?,
+        }
+    }
+    source_code.retain(|(_, line)| !line.starts_with("#"));
+
+    // Pass 8
+    // Build abstract syntax tree
+    let mut abstract_syntax_tree: Vec<Operation> = Vec::new();
+    let mut line_numbers: Vec<usize> = Vec::new();
+    for (line_number, line) in source_code {
+        let line_tokens: Vec<String> = line.split(" ").map(|x| x.to_owned()).collect();
+        // Operations prefixed with '?' only execute if the condition register is set
+        let (predicated, token) = match line_tokens[0].strip_prefix("?") {
+            Some(token) => (true, token),
+            None => (false, &line_tokens[0][..]),
+        };
+        if predicated && UNSIZED_OPERATIONS.contains(&token) {
+            return Err(halt_compilation("[E014] Only sized operations can be predicated", &line, line_number));
+        }
+        let (opcode, size) = split_operation(token, &line, line_number)?;
+        let args = line_tokens[1..].iter().map(|x|{
+            if let Some(tag) = x.strip_prefix("#") {
+                jump_addresses.get(tag).copied().ok_or_else(|| { halt_compilation("[E005] Jump address resolution failed: Try checking your spelling", &line, line_number) })
+            } else if let Some(variable) = x.strip_prefix("$") {
+                memory_map.get(variable).map(|x| x.0).ok_or_else(|| { halt_compilation("[E006] Memory resolution failed: Try checking your spelling", &line, line_number) })
+            } else if let Ok(literal) = x.parse::<usize>() {
+                Ok(literal)
+            } else {
+                Err(halt_compilation("[E007] Invalid argument to function: Only variables, tags, and literals are allowed as arguments", &line, line_number))
+            }
+        }).collect::<Result<Vec<usize>, CompileError>>()?;
+        let operation = match &opcode[..] {
+            "nop" => Operation::Nop(),
+            "mov" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Mov(size, args[0], args[1])
+            }
+            "add" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Add(size, args[0], args[1], args[2])
+            }
+            "sub" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Sub(size, args[0], args[1], args[2])
+            }
+            "mul" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Mul(size, args[0], args[1], args[2])
+            }
+            "divt" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::DivT(size, args[0], args[1], args[2])
+            }
+            "divr" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::DivR(size, args[0], args[1], args[2])
+            }
+            "rem" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Rem(size, args[0], args[1], args[2])
+            }
+            "cgt" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Cgt(size, args[0], args[1], args[2])
+            }
+            "clt" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Clt(size, args[0], args[1], args[2])
+            }
+            "jmp" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Jmp(args[0])
+            }
+            "jie" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Jie(size, args[0], args[1])
+            }
+            "jne" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Jne(size, args[0], args[1])
+            }
+            "jmpd" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::JmpD(args[0])
+            }
+            "jied" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::JieD(size, args[0], args[1])
+            }
+            "jned" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::JneD(size, args[0], args[1])
+            }
+            "puti" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::PutI(size, args[0])
+            }
+            "putc" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::PutC(size, args[0])
+            }
+            "imz" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Imz(size, args[0])
+            }
+            "equ" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 argument", &line, line_number));
+                }
+                Operation::Equ(size, args[0], args[1], args[2])
+            }
+            "and" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::And(size, args[0], args[1], args[2])
+            }
+            "or" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Or(size, args[0], args[1], args[2])
+            }
+            "xor" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Xor(size, args[0], args[1], args[2])
+            }
+            "not" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Not(size, args[0], args[1])
+            }
+            "shl" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Shl(size, args[0], args[1], args[2])
+            }
+            "shr" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Shr(size, args[0], args[1], args[2])
+            }
+            "push" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Push(size, args[0])
+            }
+            "pop" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Pop(size, args[0])
+            }
+            "call" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Call(args[0])
+            }
+            "ret" => Operation::Ret(),
+            "cge" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Cge(size, args[0], args[1], args[2])
+            }
+            "cle" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Cle(size, args[0], args[1], args[2])
+            }
+            "geti" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::GetI(size, args[0])
+            }
+            "getc" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::GetC(size, args[0])
+            }
+            "puts" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::PutS(size, args[0])
+            }
+            "memcpy" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Memcpy(args[0], args[1], args[2])
+            }
+            "memset" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Memset(args[0], args[1], args[2])
+            }
+            "swap" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Swap(size, args[0], args[1])
+            }
+            "bswap" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Bswap(size, args[0], args[1])
+            }
+            "popcnt" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Popcnt(size, args[0], args[1])
+            }
+            "clz" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Clz(size, args[0], args[1])
+            }
+            "ctz" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Ctz(size, args[0], args[1])
+            }
+            "rotl" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Rotl(size, args[0], args[1], args[2])
+            }
+            "rotr" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Rotr(size, args[0], args[1], args[2])
+            }
+            "inc" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Inc(size, args[0])
+            }
+            "dec" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Dec(size, args[0])
+            }
+            "neg" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Neg(size, args[0], args[1])
+            }
+            "abs" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Abs(size, args[0], args[1])
+            }
+            "assert" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Assert(size, args[0])
+            }
+            "nassert" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Nassert(size, args[0])
+            }
+            "trap" => Operation::Trap(),
+            "bounds" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Bounds(size, args[0], args[1], args[2], args[3])
+            }
+            "scgt" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Scgt(size, args[0], args[1], args[2])
+            }
+            "sclt" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Sclt(size, args[0], args[1], args[2])
+            }
+            "sequ" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Sequ(size, args[0], args[1], args[2])
+            }
+            "addo" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Addo(size, args[0], args[1], args[2], args[3])
+            }
+            "subo" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Subo(size, args[0], args[1], args[2], args[3])
+            }
+            "mulo" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Mulo(size, args[0], args[1], args[2], args[3])
+            }
+            "sadd" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Sadd(size, args[0], args[1], args[2])
+            }
+            "ssub" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Ssub(size, args[0], args[1], args[2])
+            }
+            "smul" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Smul(size, args[0], args[1], args[2])
+            }
+            "min" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Min(size, args[0], args[1], args[2])
+            }
+            "max" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Max(size, args[0], args[1], args[2])
+            }
+            "mod" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Mod(size, args[0], args[1], args[2])
+            }
+            "rand" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Rand(size, args[0])
+            }
+            "clock" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Clock(size, args[0])
+            }
+            "puth" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::PutH(size, args[0])
+            }
+            "strlen" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Strlen(args[0], args[1])
+            }
+            "strcmp" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Strcmp(args[0], args[1], args[2])
+            }
+            "imm" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                if size > 8 {
+                    return Err(halt_compilation("[E018] Immediate too large: Immediates can be at most 64 bits", &line, line_number));
+                }
+                Operation::Imm(size, args[0], args[1])
+            }
+            "memmove" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Memmove(args[0], args[1], args[2])
+            }
+            "prtmm" => Operation::Prtmm(),
+            "syscall" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Syscall(args[0])
+            }
+            "savefp" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Savefp(args[0])
+            }
+            "restfp" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Restfp(args[0])
+            }
+            "switch" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Switch(args[0], args[1], args[2], args[3])
+            }
+            "cosave" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Cosave(args[0])
+            }
+            "corestore" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Corestore(args[0])
+            }
+            "profr" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Profregion(args[0])
+            }
+            "rep" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Repeat(args[0])
+            }
+            "setpred" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Setpred(args[0])
+            }
+            "clrpred" => Operation::Clrpred(),
+            "addmod" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Addmod(size, args[0], args[1], args[2], args[3])
+            }
+            "mulmod" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Mulmod(size, args[0], args[1], args[2], args[3])
+            }
+            "modpow" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Modpow(size, args[0], args[1], args[2], args[3])
+            }
+            "invmod" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Invmod(size, args[0], args[1], args[2], args[3])
+            }
+            "xorsh64" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Xorshift64(args[0], args[1])
+            }
+            "chacha20" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Chacha20blk(args[0], args[1])
+            }
+            "sha256" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Sha256comp(args[0], args[1])
+            }
+            "popstk" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Popstack(args[0])
+            }
+            "peek" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Peek(size, args[0])
+            }
+            "pushpc" => Operation::Pushpc(),
+            "poppc" => Operation::Poppc(),
+            "vcmpeq8" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Vcmpeq8(args[0], args[1], args[2], args[3])
+            }
+            "vcmpgt8" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Vcmpgt8(args[0], args[1], args[2], args[3])
+            }
+            "dotprod" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Dotprod(args[0], args[1], args[2], args[3])
+            }
+            "tlookup" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                // The element size is embedded in the instruction as a single byte
+                if !matches!(line_tokens[3].parse::<u8>(), Ok(1..)) {
+                    return Err(halt_compilation("[E007] Invalid argument to function: The element size must be a literal between 1 and 255", &line, line_number));
+                }
+                Operation::Tlookup(args[0], args[1], args[2], args[3])
+            }
+            "setout" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Setout(args[0])
+            }
+            "setin" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Setin(args[0])
+            }
+            "bloopn" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Bloopn(args[0], args[1])
+            }
+            "lend" => Operation::Lend(),
+            "rfind" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Rfind(args[0], args[1], args[2], args[3])
+            }
+            "splitb" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Splitb(size, args[0], args[1])
+            }
+            "joinb" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Joinb(size, args[0], args[1])
+            }
+            "interleave" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Interleave(args[0], args[1], args[2], args[3])
+            }
+            "deinterleave" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Deinterleave(args[0], args[1], args[2], args[3])
+            }
+            "rleenc" => {
+                if args.len() != 5 {
+                    return Err(halt_compilation("[E008] This function takes 5 arguments", &line, line_number));
+                }
+                Operation::Rleenc(args[0], args[1], args[2], args[3], args[4])
+            }
+            "rledec" => {
+                if args.len() != 5 {
+                    return Err(halt_compilation("[E008] This function takes 5 arguments", &line, line_number));
+                }
+                Operation::Rledec(args[0], args[1], args[2], args[3], args[4])
+            }
+            "deltaenc" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Deltaenc(args[0], args[1], args[2])
+            }
+            "deltadec" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Deltadec(args[0], args[1], args[2])
+            }
+            "nibpk" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Nibpk(args[0], args[1], args[2])
+            }
+            "nibupk" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Nibupk(args[0], args[1], args[2])
+            }
+            "fibstep" => {
+                if args.len() != 2 {
+                    return Err(halt_compilation("[E008] This function takes 2 arguments", &line, line_number));
+                }
+                Operation::Fibstep(args[0], args[1])
+            }
+            "bsearch" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Bsearch(args[0], args[1], args[2], args[3])
+            }
+            "qsort" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Qsort(args[0], args[1], args[2], args[3])
+            }
+            "uniqsort" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Uniqsort(args[0], args[1], args[2])
+            }
+            "pctile" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Pctile(args[0], args[1], args[2], args[3])
+            }
+            "histo" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Histo(args[0], args[1], args[2])
+            }
+            "wsample" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Wsample(args[0], args[1], args[2], args[3])
+            }
+            "bitmatmul" => {
+                if args.len() != 6 {
+                    return Err(halt_compilation("[E008] This function takes 6 arguments", &line, line_number));
+                }
+                Operation::Bitmatmul(args[0], args[1], args[2], args[3], args[4], args[5])
+            }
+            "popcnta" => {
+                if args.len() != 3 {
+                    return Err(halt_compilation("[E008] This function takes 3 arguments", &line, line_number));
+                }
+                Operation::Popcnta(args[0], args[1], args[2])
+            }
+            "dither" => {
+                if args.len() != 4 {
+                    return Err(halt_compilation("[E008] This function takes 4 arguments", &line, line_number));
+                }
+                Operation::Dither(args[0], args[1], args[2], args[3])
+            }
+            "hlte" => {
+                if args.len() != 1 {
+                    return Err(halt_compilation("[E008] This function takes 1 argument", &line, line_number));
+                }
+                Operation::Hlte(size, args[0])
+            }
+            "hlt" => Operation::Hlt(),
+            _ => {
+                return Err(halt_compilation("[E009] Invalid opcode. Check your spelling", &line, line_number));
+            }
+        };
+        abstract_syntax_tree.push(if predicated {
+            Operation::Predicated(Box::new(operation))
+        } else {
+            operation
+        });
+        line_numbers.push(line_number);
+    }
+
+    Ok((abstract_syntax_tree, memory_map, metadata, line_numbers))
+}
+
+/// Encodes a standard 14 byte instruction: opcode, size byte, and the addresses of source1, source2,
+/// and destination
+pub fn gen_binary_instruction(
+    opcode: u8,
+    size: usize,
+    src1: usize,
+    src2: usize,
+    dest: usize,
+) -> [u8; 14] {
+    let mut instruction = [0u8; 14];
+    instruction[0] = opcode;
+    instruction[1] = size as u8;
+    instruction[2..6].copy_from_slice(&(src1 as u32).to_be_bytes());
+    instruction[6..10].copy_from_slice(&(src2 as u32).to_be_bytes());
+    instruction[10..14].copy_from_slice(&(dest as u32).to_be_bytes());
+    instruction
+}
+
+fn gen_sized_instruction(opcode: u8, size: usize, addresses: &[usize]) -> Vec<u8> {
+    let mut instruction = vec![opcode, size as u8];
+    for address in addresses {
+        instruction.extend_from_slice(&(*address as u32).to_be_bytes());
+    }
+    instruction
+}
+
+fn gen_address_instruction(opcode: u8, addresses: &[usize]) -> Vec<u8> {
+    let mut instruction = vec![opcode];
+    for address in addresses {
+        instruction.extend_from_slice(&(*address as u32).to_be_bytes());
+    }
+    instruction
+}
+
+/// Encodes the abstract syntax tree followed by the initial values of its variables into a
+/// transient image, starting with the header described in `transient_asm::image`. If
+/// `line_numbers` holds the source line of every operation, the image gets a debug section that
+/// maps instructions back to them in the source file identified by `file_hash`.
+pub fn codegen(
+    abstract_syntax_tree: &[Operation],
+    memory_map: &MemoryMap,
+    metadata: &Metadata,
+    line_numbers: &[usize],
+    file_hash: u32,
+) -> Vec<u8> {
+    let mut image: Vec<u8> = vec![];
+    let mut debug_info: Vec<(usize, usize)> = vec![];
+
+    // Write instructions to image
+    for (index, instruction) in abstract_syntax_tree.iter().enumerate() {
+        let opcode = resolve_operation_opcode(instruction);
+        let start = image.len();
+        if let Some(line_number) = line_numbers.get(index) {
+            debug_info.push((start, *line_number));
+        }
+        let (instruction, predicated) = match instruction {
+            Operation::Predicated(operation) => (&**operation, true),
+            operation => (operation, false),
+        };
+        match *instruction {
+            Operation::Nop() => {
+                image.push(opcode);
+            }
+            Operation::Mov(size, src1, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, dest));
+            }
+            Operation::Add(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Sub(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Mul(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::DivT(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::DivR(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Rem(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Cgt(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Clt(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Jmp(src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, 0x00, src1, 0x00, 0x00));
+            }
+            Operation::Jie(size, src1, src2) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, 0x00));
+            }
+            Operation::Jne(size, src1, src2) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, 0x00));
+            }
+            Operation::JmpD(src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, DELAY_SLOT_FLAG, src1, 0x00, 0x00));
+            }
+            Operation::JieD(size, src1, src2) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size | DELAY_SLOT_FLAG, src1, src2, 0x00));
+            }
+            Operation::JneD(size, src1, src2) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size | DELAY_SLOT_FLAG, src1, src2, 0x00));
+            }
+            Operation::PutI(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::PutC(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::Imz(size, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, 0x00, 0x00, dest));
+            }
+            Operation::Equ(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::And(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Or(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Xor(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Not(size, src1, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, dest));
+            }
+            Operation::Shl(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Shr(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Push(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::Pop(size, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, 0x00, 0x00, dest));
+            }
+            Operation::Call(target) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[target]));
+            }
+            Operation::Ret() => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[]));
+            }
+            Operation::Cge(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Cle(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::GetI(size, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, 0x00, 0x00, dest));
+            }
+            Operation::GetC(size, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, 0x00, 0x00, dest));
+            }
+            Operation::PutS(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::Memcpy(dest, src, len) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[dest, src, len]));
+            }
+            Operation::Memset(dest, val, len) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[dest, val, len]));
+            }
+            Operation::Swap(size, src1, src2) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, 0x00));
+            }
+            Operation::Bswap(size, src1, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, dest));
+            }
+            Operation::Popcnt(size, src1, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, dest));
+            }
+            Operation::Clz(size, src1, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, dest));
+            }
+            Operation::Ctz(size, src1, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, dest));
+            }
+            Operation::Rotl(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Rotr(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Inc(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::Dec(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::Neg(size, src1, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, dest));
+            }
+            Operation::Abs(size, src1, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, dest));
+            }
+            Operation::Assert(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::Nassert(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::Trap() => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[]));
+            }
+            Operation::Bounds(size, value, low, high, dest) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[value, low, high, dest]));
+            }
+            Operation::Scgt(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Sclt(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Sequ(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Addo(size, src1, src2, dest, overflow) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[src1, src2, dest, overflow]));
+            }
+            Operation::Subo(size, src1, src2, dest, overflow) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[src1, src2, dest, overflow]));
+            }
+            Operation::Mulo(size, src1, src2, dest, overflow) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[src1, src2, dest, overflow]));
+            }
+            Operation::Sadd(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Ssub(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Smul(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Min(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Max(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Mod(size, src1, src2, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, src2, dest));
+            }
+            Operation::Rand(size, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, 0x00, 0x00, dest));
+            }
+            Operation::Clock(size, dest) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, 0x00, 0x00, dest));
+            }
+            Operation::PutH(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::Strlen(src, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, dest]));
+            }
+            Operation::Strcmp(src1, src2, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src1, src2, dest]));
+            }
+            Operation::Imm(size, value, dest) => {
+                image.extend_from_slice(&[opcode, size as u8]);
+                image.extend_from_slice(&(value as u64).to_be_bytes()[8 - size..]);
+                image.extend_from_slice(&(dest as u32).to_be_bytes());
+            }
+            Operation::Memmove(len, src, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[len, src, dest]));
+            }
+            Operation::Prtmm() => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[]));
+            }
+            Operation::Syscall(id) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[id]));
+            }
+            Operation::Savefp(dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[dest]));
+            }
+            Operation::Restfp(src1) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src1]));
+            }
+            Operation::Switch(value, table_base, table_len, default) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[value, table_base, table_len, default]));
+            }
+            Operation::Cosave(context) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[context]));
+            }
+            Operation::Corestore(context) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[context]));
+            }
+            Operation::Profregion(id) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[id]));
+            }
+            Operation::Repeat(count) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[count]));
+            }
+            Operation::Setpred(src1) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src1]));
+            }
+            Operation::Clrpred() => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[]));
+            }
+            Operation::Addmod(size, src1, src2, modulus, dest) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[src1, src2, modulus, dest]));
+            }
+            Operation::Mulmod(size, src1, src2, modulus, dest) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[src1, src2, modulus, dest]));
+            }
+            Operation::Modpow(size, base, exponent, modulus, dest) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[base, exponent, modulus, dest]));
+            }
+            Operation::Invmod(size, value, modulus, dest, ok) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[value, modulus, dest, ok]));
+            }
+            Operation::Xorshift64(state, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[state, dest]));
+            }
+            Operation::Chacha20blk(state, out) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[state, out]));
+            }
+            Operation::Sha256comp(state, block) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[state, block]));
+            }
+            Operation::Popstack(count) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[count]));
+            }
+            Operation::Peek(size, dest) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[dest]));
+            }
+            Operation::Pushpc() => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[]));
+            }
+            Operation::Poppc() => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[]));
+            }
+            Operation::Vcmpeq8(src1, src2, count, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src1, src2, count, dest]));
+            }
+            Operation::Vcmpgt8(src1, src2, count, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src1, src2, count, dest]));
+            }
+            Operation::Dotprod(len, src1, src2, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[len, src1, src2, dest]));
+            }
+            Operation::Tlookup(index, table_base, elem_size, dest) => {
+                // The element size is a single byte embedded in the instruction
+                image.extend_from_slice(&gen_address_instruction(opcode, &[index, table_base]));
+                image.push(elem_size as u8);
+                image.extend_from_slice(&(dest as u32).to_be_bytes());
+            }
+            Operation::Setout(device) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[device]));
+            }
+            Operation::Setin(device) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[device]));
+            }
+            Operation::Bloopn(count, body) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[count, body]));
+            }
+            Operation::Lend() => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[]));
+            }
+            Operation::Rfind(base, len, value, result) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[base, len, value, result]));
+            }
+            Operation::Splitb(size, src, dest) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[src, dest]));
+            }
+            Operation::Joinb(size, src, dest) => {
+                image.extend_from_slice(&gen_sized_instruction(opcode, size, &[src, dest]));
+            }
+            Operation::Interleave(ch1, ch2, count, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[ch1, ch2, count, dest]));
+            }
+            Operation::Deinterleave(src, count, ch1, ch2) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, count, ch1, ch2]));
+            }
+            Operation::Rleenc(src, len, dest, capacity, out_len) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, len, dest, capacity, out_len]));
+            }
+            Operation::Rledec(src, len, dest, capacity, out_len) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, len, dest, capacity, out_len]));
+            }
+            Operation::Deltaenc(src, len, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, len, dest]));
+            }
+            Operation::Deltadec(src, len, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, len, dest]));
+            }
+            Operation::Nibpk(src, count, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, count, dest]));
+            }
+            Operation::Nibupk(src, count, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, count, dest]));
+            }
+            Operation::Fibstep(a, b) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[a, b]));
+            }
+            Operation::Bsearch(base, count, target, result) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[base, count, target, result]));
+            }
+            Operation::Qsort(base, count, element_size, descending) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[base, count, element_size, descending]));
+            }
+            Operation::Uniqsort(base, count, out_count) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[base, count, out_count]));
+            }
+            Operation::Pctile(base, count, percentile, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[base, count, percentile, dest]));
+            }
+            Operation::Histo(src, len, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, len, dest]));
+            }
+            Operation::Wsample(weights, count, state, result) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[weights, count, state, result]));
+            }
+            Operation::Bitmatmul(rows, cols, shared, mat1, mat2, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[rows, cols, shared, mat1, mat2, dest]));
+            }
+            Operation::Popcnta(base, len, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[base, len, dest]));
+            }
+            Operation::Dither(src, width, height, dest) => {
+                image.extend_from_slice(&gen_address_instruction(opcode, &[src, width, height, dest]));
+            }
+            Operation::Hlte(size, src1) => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, size, src1, 0x00, 0x00));
+            }
+            Operation::Hlt() => {
+                image.extend_from_slice(&gen_binary_instruction(opcode, 0x00, 0x00, 0x00, 0x00));
+            }
+            Operation::Predicated(..) => {
+                unreachable!("[COMPILER PANIC]: Nested predicated operation");
+            }
+        }
+        if predicated {
+            image[start + 1] |= PREDICATE_FLAG;
+        }
+    }
+
+    // Calculate amount of space that variables take
+    let code_length = image.len();
+    let mut var_size = 0;
+    for (_address, _value, size) in memory_map.values() {
+        var_size += size;
+    }
+
+    // Allocate size for new vars
+    image.resize(image.len() + var_size, 0);
+
+    // Write variables to image
+    for (address, value, size) in memory_map.values() {
+        match value {
+            Initializer::Integer(value) => image[*address..][..*size].copy_from_slice(
+                value.to_be_bytes()[value.to_be_bytes().len() - size..]
+                    .try_into()
+                    .expect("[COMPILER PANIC]: Failed to write variable to image"),
+            ),
+            Initializer::String(bytes) => image[*address..][..*size].copy_from_slice(bytes),
+        }
+    }
+
+    let mut sections = vec![];
+    if !metadata.is_empty() {
+        sections.push((METADATA_MARKER, encode_metadata(metadata)));
+    }
+    if !debug_info.is_empty() {
+        sections.push((DEBUG_MARKER, encode_debug_info(&debug_info, file_hash)));
+    }
+    if !memory_map.is_empty() {
+        let symbols: Vec<(String, usize, usize)> = memory_map
+            .iter()
+            .map(|(name, (address, _value, size))| (name.clone(), *address, *size))
+            .collect();
+        sections.push((SYMBOL_MARKER, encode_symbols(&symbols)));
+    }
+    write_image(code_length, &image, &sections)
+}
+
+/// Builds the error for a line that failed to compile
+fn halt_compilation(message: &'static str, line: &str, line_number: usize) -> CompileError {
+    // Messages start with their code, e.g. "[E006] Memory resolution failed"
+    let (code, message) = message.split_once(' ').unwrap_or(("", message));
+    CompileError {
+        code: code.trim_start_matches('[').trim_end_matches(']'),
+        message,
+        line: line.to_owned(),
+        line_number,
+    }
+}
+
+/// Formats the abstract syntax tree with one operation per line, prefixed by its address
+pub fn format_ast(ast: &Vec<Operation>) -> String {
+    let mut out = String::new();
+    let mut address = 0;
+    for operation in ast {
+        out += &format!("{:#06X}: {:?}\n", address, operation);
+        address += codegen(std::slice::from_ref(operation), &MemoryMap::new(), &Metadata::new(), &[], 0).len() - HEADER_SIZE;
+    }
+    out
+}
+
+/// Formats the memory map with one variable per line, sorted by address
+pub fn format_mm(mm: &MemoryMap) -> String {
+    let mut variables: Vec<_> = mm.iter().collect();
+    variables.sort_by_key(|(_name, (address, _value, _size))| *address);
+    let mut out = String::new();
+    for (name, (address, value, size)) in variables {
+        out += &format!("[{}]: {} = {} ({}b)\n", address, name, value, size);
+    }
+    out
+}
+
+/// Reads a project file listing `[[sources]]` entries with a `path` in link order, and returns
+/// the linked source code. Tags are namespaced by the file they are declared in (e.g.
+/// `main::loop`), unless a file exports them with `export #tag`.
+pub fn load_project(project_path: &str) -> Result<Vec<(usize, String)>, TransientError> {
+    let project = std::fs::read_to_string(project_path)?;
+    let mut sources: Vec<String> = vec![];
+    for (index, line) in project.lines().map(|x| x.trim()).enumerate() {
+        if line == "[[sources]]" {
+            sources.push(String::new());
+        } else if let Some(value) = line.strip_prefix("path") {
+            let path = value.trim_start().trim_start_matches("=").trim().trim_matches('"');
+            match sources.last_mut() {
+                Some(source) => *source = path.to_owned(),
+                None => return Err(halt_compilation("[E015] Project file path outside of a [[sources]] entry", line, index + 1).into()),
+            }
+        }
+    }
+
+    // Source paths are relative to the project file
+    let base = Path::new(project_path).parent().unwrap_or(Path::new(""));
+    let files = sources
+        .iter()
+        .map(|source| {
+            let path = base.join(source);
+            let source_code = std::fs::read_to_string(&path)?;
+            let namespace = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            Ok((namespace, source_code.split("\n").map(|x| x.to_owned()).collect()))
+        })
+        .collect::<Result<Vec<(String, Vec<String>)>, TransientError>>()?;
+
+    // Collect exported tags from every file before namespacing
+    let exports: HashSet<String> = files
+        .iter()
+        .flat_map(|(_namespace, lines)| lines.iter())
+        .filter_map(|line| line.strip_prefix("export #"))
+        .map(|x| x.to_owned())
+        .collect();
+
+    let mut source_code: Vec<(usize, String)> = vec![];
+    for (namespace, lines) in files {
+        for (index, line) in lines.iter().enumerate().filter(|(_, x)| !x.starts_with("export #")) {
+            source_code.push((index + 1, namespace_tags(line, &namespace, &exports)));
+        }
+    }
+    Ok(source_code)
+}
+
+/// Prefixes every tag on a line that isn't exported with `namespace::`. Comments, macros such as
+/// `#!fatal`, and string literals are left untouched.
+fn namespace_tags(line: &str, namespace: &str, exports: &HashSet<String>) -> String {
+    if line.trim_start().starts_with("//") || line.trim_start().starts_with("#!") {
+        return line.to_owned();
+    }
+    // String literals are always the last argument of a line
+    let (code, literal) = line.split_at(line.find('"').unwrap_or(line.len()));
+    let tokens: Vec<String> = code
+        .split(" ")
+        .map(|token| match token.strip_prefix("#") {
+            Some(tag) if !exports.contains(tag) => format!("#{}::{}", namespace, tag),
+            _ => token.to_owned(),
+        })
+        .collect();
+    tokens.join(" ") + literal
+}
+
+/// Compiles TIR source code into a transient image
+pub fn compile(source_code: &str) -> Result<Vec<u8>, CompileError> {
+    let hash = file_hash(source_code.as_bytes());
+    let source_code = source_code.split("\n").map(|x| x.to_owned()).enumerate().map(|(index, x)| (index + 1, x)).collect();
+    let (abstract_syntax_tree, mut memory_map, metadata, line_numbers) = preprocess_source_code(lower_immediates(source_code))?;
+    let abstract_syntax_tree = optimize_constants(abstract_syntax_tree, &mut memory_map);
+    Ok(codegen(&abstract_syntax_tree, &memory_map, &metadata, &line_numbers, hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::tests::run;
+
+    /// Writes the files of a project into a fresh temporary directory and returns the path of its
+    /// project file, which lists the files in order
+    fn write_project(name: &str, files: &[(&str, &str)]) -> String {
+        let directory = std::env::temp_dir().join(format!("transient-asm-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut project = String::new();
+        for (file, source_code) in files {
+            std::fs::write(directory.join(file), source_code).unwrap();
+            project += &format!("[[sources]]\npath = \"{}\"\n", file);
+        }
+        let project_path = directory.join("project.toml");
+        std::fs::write(&project_path, project).unwrap();
+        project_path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn namespace_tags_skips_comments_and_string_literals() {
+        let exports = HashSet::from(["shared".to_owned()]);
+        assert_eq!(namespace_tags("jmp64 #loop", "main", &exports), "jmp64 #main::loop");
+        assert_eq!(namespace_tags("#loop", "main", &exports), "#main::loop");
+        assert_eq!(namespace_tags("jmp64 #shared", "main", &exports), "jmp64 #shared");
+        assert_eq!(namespace_tags("// jump to #loop", "main", &exports), "// jump to #loop");
+        assert_eq!(namespace_tags("setstr $text \"see #loop and #shared\"", "main", &exports), "setstr $text \"see #loop and #shared\"");
+        assert_eq!(namespace_tags("#!fatal \"no #loop\" 2", "main", &exports), "#!fatal \"no #loop\" 2");
+    }
+
+    #[test]
+    fn load_project_namespaces_tags_per_file() {
+        let project = write_project(
+            "namespaces",
+            &[
+                ("main.tasm", "setstr $text \"#1 of #2\"\njmp64 #start\n#start\njmp64 #done"),
+                ("lib.tasm", "export #done\n#start\n#done\nhlt64"),
+            ],
+        );
+        let lines: Vec<String> = load_project(&project).unwrap().into_iter().map(|(_, line)| line).collect();
+        assert_eq!(lines, ["setstr $text \"#1 of #2\"", "jmp64 #main::start", "#main::start", "jmp64 #done", "#lib::start", "#done", "hlt64"]);
+    }
+
+    #[test]
+    fn tlookup_element_size_must_be_a_byte_literal() {
+        let source_code = |size: &str| format!("set64 $i 0\nset64 $t 0\nset64 $r 0\ntlookup $i $t {} $r\nhlt64", size);
+        assert!(compile(&source_code("1")).is_ok());
+        assert!(compile(&source_code("255")).is_ok());
+        for size in ["0", "256", "$t"] {
+            assert_eq!(compile(&source_code(size)).unwrap_err().code, "E007");
+        }
+    }
+
+    #[test]
+    fn jumps_to_tags_declared_further_down() {
+        let run = run(include_str!("../examples/forward_jump.tasm"));
+        assert_eq!(run.console.contents(), "2\n");
+    }
+
+    /// Parses a program into its folded operations
+    fn fold(source_code: &str) -> Vec<Operation> {
+        let source_code = source_code.lines().map(|x| x.to_owned()).enumerate().map(|(index, x)| (index + 1, x)).collect();
+        let (abstract_syntax_tree, mut memory_map, ..) = preprocess_source_code(lower_immediates(source_code)).unwrap();
+        optimize_constants(abstract_syntax_tree, &mut memory_map)
+    }
+
+    #[test]
+    fn folds_arithmetic_on_two_intermediates() {
+        let source_code = "set64 $result 0\nadd64 !64_10 !64_5 $result\nmul8 !8_16 !8_16 $result\nhlt64";
+        assert!(matches!(fold(source_code)[..], [Operation::Mov(8, ..), Operation::Mov(1, ..), Operation::Hlt()]));
+        let run = run("set64 $result 0\nset8 $wrapped 0\nadd64 !64_10 !64_5 $result\nsub8 !8_1 !8_2 $wrapped\nhlt64");
+        assert_eq!(run.value("result"), 15);
+        assert_eq!(run.value("wrapped"), 255);
+    }
+
+    #[test]
+    fn leaves_variables_mismatched_sizes_and_divisions_by_zero() {
+        let source_code = "set64 $i 0\nadd64 $i !64_2 $i\ndivt64 !64_1 !64_0 $i\nadd32 !64_1 !64_1 $i\nhlt64";
+        assert!(matches!(fold(source_code)[..], [Operation::Add(8, ..), Operation::DivT(8, ..), Operation::Add(4, ..), Operation::Hlt()]));
+    }
+
+    #[test]
+    fn folds_in_place_adds_of_one() {
+        let source_code = "set64 $i 0\nset64 $j 0\nadd64 $i !64_1 $i\nadd64 !64_1 $i $i\nsub64 $i !64_1 $i\nadd64 $i !64_1 $j\nhlt64";
+        assert!(matches!(fold(source_code)[..], [Operation::Inc(8, ..), Operation::Inc(8, ..), Operation::Dec(8, ..), Operation::Add(8, ..), Operation::Hlt()]));
+        let run = run("set64 $i 5\nadd64 $i !64_1 $i\nadd64 $i !64_1 $i\nsub64 $i !64_1 $i\nhlt64");
+        assert_eq!(run.value("i"), 6);
+    }
+
+    #[test]
+    fn expands_fatal_macros_in_projects() {
+        let project = write_project(
+            "fatal",
+            &[("main.tasm", "jmp64 #fail"), ("lib.tasm", "export #fail\n#fail\n#!fatal \"failed\\n\"")],
+        );
+        let lines: Vec<String> = load_project(&project).unwrap().into_iter().map(|(_, line)| line).collect();
+        let run = run(&lines.join("\n"));
+        assert_eq!(run.console.contents(), "failed\n");
+    }
+
+    #[test]
+    fn debug_info_identifies_the_source_file() {
+        let source_code = "set64 $x 1\nputi64 $x\nhlt64";
+        let debug_info = crate::image::read_debug_info(&compile(source_code).unwrap()).unwrap();
+        let hash = file_hash(source_code.as_bytes());
+        assert_ne!(hash, 0);
+        assert_eq!(debug_info, [(0, 2, hash), (14, 3, hash)]);
+    }
+}
@@ -0,0 +1,113 @@
+//! Errors produced by the transient compiler and virtual machine.
+
+use alloc::string::String;
+use core::fmt;
+
+/// Errors raised by the compiler and the virtual machine
+#[derive(Debug)]
+pub enum TransientError {
+    /// The processor fetched a byte that isn't a known opcode
+    InvalidOpcode(u8),
+    /// A memory access reached past the end of transient memory
+    OutOfBoundsAccess { addr: usize, limit: usize },
+    /// A memory access or the program counter left the regions allowed by the sandbox
+    SandboxViolation { addr: usize },
+    /// A division, remainder, or modular operation was given a zero divisor
+    DivisionByZero,
+    /// The stack grew into the loaded image
+    StackOverflow,
+    /// More bytes were popped, peeked, or discarded than the stack holds
+    StackUnderflow,
+    /// A memory access used a pointer mode other than 0, 1, or 2
+    InvalidPointerMode(u8),
+    /// SYSCALL was invoked with an id outside of the dispatch table
+    InvalidSyscall(u32),
+    /// SETOUT or SETIN referred to a device id that was never registered
+    UnknownDevice(u32),
+    /// An instruction was given operands it can't work with
+    InvalidOperand(&'static str),
+    /// An ASSERT or NASSERT at `pc` found an unexpected value at `addr`
+    AssertionFailed { addr: usize, pc: usize },
+    /// The result of a signed operation doesn't fit in the operand size
+    Overflow,
+    /// The processor was asked to step after it halted
+    AlreadyHalted,
+    /// The image doesn't fit in transient memory at the requested offset
+    ImageTooLarge { size: usize, limit: usize },
+    /// The image has a missing or corrupted header
+    InvalidImageFormat(&'static str),
+    /// The compiler rejected a line of source code
+    CompilationError(CompileError),
+    /// GET_I or GET_C reached the end of the input
+    EndOfInput,
+    /// GET_I read something other than a decimal integer
+    InvalidInput(&'static str),
+    /// Reading or writing a host file or device failed
+    #[cfg(feature = "std")]
+    IoError(std::io::Error),
+}
+
+impl fmt::Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransientError::InvalidOpcode(opcode) => write!(f, "Invalid opcode {:#04X}", opcode),
+            TransientError::OutOfBoundsAccess { addr, limit } => write!(f, "Memory access at {:#06X} is outside of the {} bytes of transient memory", addr, limit),
+            TransientError::SandboxViolation { addr } => write!(f, "Sandbox violation: Access at {:#06X} is outside of the allowed regions", addr),
+            TransientError::DivisionByZero => write!(f, "Division by zero"),
+            TransientError::StackOverflow => write!(f, "Stack overflow: The stack collided with the image"),
+            TransientError::StackUnderflow => write!(f, "Stack underflow: The stack holds fewer bytes than requested"),
+            TransientError::InvalidPointerMode(mode) => write!(f, "Invalid pointer mode {}", mode),
+            TransientError::InvalidSyscall(id) => write!(f, "Invalid syscall id {}", id),
+            TransientError::UnknownDevice(id) => write!(f, "No device registered with id {}", id),
+            TransientError::InvalidOperand(message) => write!(f, "Invalid operand: {}", message),
+            TransientError::AssertionFailed { addr, pc } => write!(f, "Assertion at {:#06X} failed for the value at {:#06X}", pc, addr),
+            TransientError::Overflow => write!(f, "Arithmetic overflow: The result doesn't fit in the operand size"),
+            TransientError::AlreadyHalted => write!(f, "The processor is halted"),
+            TransientError::ImageTooLarge { size, limit } => write!(f, "Image of {} bytes does not fit in {} bytes of transient memory", size, limit),
+            TransientError::InvalidImageFormat(message) => write!(f, "Invalid image format: {}", message),
+            TransientError::CompilationError(error) => write!(f, "{}", error),
+            TransientError::EndOfInput => write!(f, "End of input"),
+            TransientError::InvalidInput(message) => write!(f, "Invalid input: {}", message),
+            #[cfg(feature = "std")]
+            TransientError::IoError(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransientError {}
+
+impl From<CompileError> for TransientError {
+    fn from(error: CompileError) -> Self {
+        TransientError::CompilationError(error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for TransientError {
+    fn from(error: std::io::Error) -> Self {
+        TransientError::IoError(error)
+    }
+}
+
+/// A line of source code that the compiler rejected
+#[derive(Debug)]
+pub struct CompileError {
+    /// Error code, e.g. `E006`
+    pub code: &'static str,
+    /// What went wrong, and a hint at how to fix it
+    pub message: &'static str,
+    /// The offending line
+    pub line: String,
+    /// 1-based number of the offending line in its source file
+    pub line_number: usize,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}\n-> Line {}: `{}`", self.code, self.message, self.line_number, self.line)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompileError {}
@@ -0,0 +1,235 @@
+//! Layout of transient images, shared by the compiler, the virtual machine, and the disassembler.
+//!
+//! An image starts with a 16 byte header, followed by the code and data that are loaded into
+//! transient memory:
+//! - magic[4]: `TRNT`
+//! - version[2]
+//! - code_length[4]: length of the code section
+//! - image_size[4]: length of the code and data sections together
+//! - checksum[2]: CRC-16 of every byte after the header
+//!
+//! Optional sections follow the data, each made of a 4 byte marker, the length of its contents as
+//! 4 bytes, and the contents:
+//! - `META`: metadata from `@key "value"` annotations, as pairs of a key and a value that are each
+//!   prefixed with their length as 1 byte
+//! - `DBUG`: the source line of every instruction, as entries of the instruction's address[4], the
+//!   line number[2], and a hash of the source file[4]. The hash is the FNV-1a hash of the source
+//!   code the image was compiled from, or of the files of a project joined in order, so that a
+//!   line can be matched against the source it came from.
+//! - `SYMB`: the variables of the program sorted by address, as entries of the length of the
+//!   name[1], the name, the address[4], and the size[4]
+//!
+//! All fields are big-endian.
+
+use crate::error::TransientError;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+pub const MAGIC: [u8; 4] = *b"TRNT";
+pub const VERSION: u16 = 1;
+pub const HEADER_SIZE: usize = 16;
+pub const METADATA_MARKER: [u8; 4] = *b"META";
+pub const DEBUG_MARKER: [u8; 4] = *b"DBUG";
+pub const SYMBOL_MARKER: [u8; 4] = *b"SYMB";
+
+/// The marker and contents of a section
+pub type Section<'a> = ([u8; 4], &'a [u8]);
+
+/// Key-value pairs describing a program, such as its name and author
+pub type Metadata = Vec<(String, String)>;
+
+/// The header at the start of every image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageHeader {
+    pub version: u16,
+    pub code_length: usize,
+    pub image_size: usize,
+}
+
+/// Prepends a header to the code and data that are loaded into transient memory, and appends the
+/// given sections after them. `code_length` is the number of bytes of code at the start of
+/// `memory`.
+pub fn write_image(code_length: usize, memory: &[u8], sections: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut body = memory.to_vec();
+    for (marker, contents) in sections {
+        body.extend_from_slice(marker);
+        body.extend_from_slice(&(contents.len() as u32).to_be_bytes());
+        body.extend_from_slice(contents);
+    }
+    let mut image = Vec::with_capacity(HEADER_SIZE + body.len());
+    image.extend_from_slice(&MAGIC);
+    image.extend_from_slice(&VERSION.to_be_bytes());
+    image.extend_from_slice(&(code_length as u32).to_be_bytes());
+    image.extend_from_slice(&(memory.len() as u32).to_be_bytes());
+    image.extend_from_slice(&crc16(&body).to_be_bytes());
+    image.extend_from_slice(&body);
+    image
+}
+
+/// Validates the header of an image, and returns it with the bytes that follow it
+pub fn read_image(image: &[u8]) -> Result<(ImageHeader, &[u8]), TransientError> {
+    if image.len() < HEADER_SIZE || image[0..4] != MAGIC {
+        return Err(TransientError::InvalidImageFormat("Missing TRNT header: Is this a transient image?"));
+    }
+    let field = |range: Range<usize>| image[range].iter().fold(0usize, |value, byte| value << 8 | *byte as usize);
+    let header = ImageHeader {
+        version: field(4..6) as u16,
+        code_length: field(6..10),
+        image_size: field(10..14),
+    };
+    if header.version != VERSION {
+        return Err(TransientError::InvalidImageFormat("Unsupported image version"));
+    }
+    let body = &image[HEADER_SIZE..];
+    if header.code_length > header.image_size || header.image_size > body.len() {
+        return Err(TransientError::InvalidImageFormat("Image is shorter than its header states"));
+    }
+    if crc16(body) != field(14..16) as u16 {
+        return Err(TransientError::InvalidImageFormat("Checksum mismatch: The image is corrupted"));
+    }
+    Ok((header, body))
+}
+
+/// Returns the marker and contents of every section that follows the code and data of an image
+pub fn read_sections<'a>(header: &ImageHeader, body: &'a [u8]) -> Result<Vec<Section<'a>>, TransientError> {
+    let mut sections = vec![];
+    let mut rest = &body[header.image_size..];
+    while !rest.is_empty() {
+        let contents = rest
+            .get(4..8)
+            .map(|length| length.iter().fold(0usize, |value, byte| value << 8 | *byte as usize))
+            .and_then(|length| rest.get(8..8 + length))
+            .ok_or(TransientError::InvalidImageFormat("Truncated section"))?;
+        sections.push(([rest[0], rest[1], rest[2], rest[3]], contents));
+        rest = &rest[8 + contents.len()..];
+    }
+    Ok(sections)
+}
+
+/// Encodes metadata into the contents of a `META` section. Keys and values are cut off at 255
+/// bytes.
+pub fn encode_metadata(metadata: &Metadata) -> Vec<u8> {
+    let mut contents = vec![];
+    for (key, value) in metadata {
+        for text in [key, value] {
+            let bytes = &text.as_bytes()[..text.len().min(255)];
+            contents.push(bytes.len() as u8);
+            contents.extend_from_slice(bytes);
+        }
+    }
+    contents
+}
+
+/// Validates an image and returns the contents of every section with the given marker
+fn find_sections(image: &[u8], marker: [u8; 4]) -> Result<Vec<&[u8]>, TransientError> {
+    let (header, body) = read_image(image)?;
+    Ok(read_sections(&header, body)?
+        .into_iter()
+        .filter(|(x, _)| *x == marker)
+        .map(|(_, contents)| contents)
+        .collect())
+}
+
+/// Validates an image and returns the metadata in its `META` section, which is empty if it has none
+pub fn read_metadata(image: &[u8]) -> Result<Metadata, TransientError> {
+    let mut metadata = vec![];
+    for mut contents in find_sections(image, METADATA_MARKER)? {
+        let mut strings = vec![];
+        while let Some((length, rest)) = contents.split_first() {
+            let text = rest.get(..*length as usize).ok_or(TransientError::InvalidImageFormat("Truncated metadata"))?;
+            strings.push(String::from_utf8_lossy(text).into_owned());
+            contents = &rest[text.len()..];
+        }
+        if strings.len() % 2 != 0 {
+            return Err(TransientError::InvalidImageFormat("Metadata key without a value"));
+        }
+        for pair in strings.chunks(2) {
+            metadata.push((pair[0].clone(), pair[1].clone()));
+        }
+    }
+    Ok(metadata)
+}
+
+/// Encodes the address and source line number of every instruction into the contents of a `DBUG`
+/// section, along with the hash of the source file from `file_hash`. Line numbers above 65535 are
+/// stored as 65535.
+pub fn encode_debug_info(debug_info: &[(usize, usize)], file_hash: u32) -> Vec<u8> {
+    let mut contents = vec![];
+    for (address, line_number) in debug_info {
+        contents.extend_from_slice(&(*address as u32).to_be_bytes());
+        contents.extend_from_slice(&((*line_number).min(u16::MAX as usize) as u16).to_be_bytes());
+        contents.extend_from_slice(&file_hash.to_be_bytes());
+    }
+    contents
+}
+
+/// Validates an image and returns the address, source line number, and source file hash of every
+/// instruction in its `DBUG` section, sorted by address. The list is empty if the image has no
+/// debug section.
+pub fn read_debug_info(image: &[u8]) -> Result<Vec<(usize, usize, u32)>, TransientError> {
+    let mut debug_info = vec![];
+    for contents in find_sections(image, DEBUG_MARKER)? {
+        if contents.len() % 10 != 0 {
+            return Err(TransientError::InvalidImageFormat("Truncated debug entry"));
+        }
+        for entry in contents.chunks(10) {
+            let address = entry[0..4].iter().fold(0usize, |value, byte| value << 8 | *byte as usize);
+            let line_number = (entry[4] as usize) << 8 | entry[5] as usize;
+            let file_hash = u32::from_be_bytes([entry[6], entry[7], entry[8], entry[9]]);
+            debug_info.push((address, line_number, file_hash));
+        }
+    }
+    debug_info.sort();
+    Ok(debug_info)
+}
+
+/// Encodes the name, address, and size of every variable into the contents of a `SYMB` section,
+/// sorted by address. Variables with names longer than 255 bytes are left out.
+pub fn encode_symbols(symbols: &[(String, usize, usize)]) -> Vec<u8> {
+    let mut symbols: Vec<&(String, usize, usize)> = symbols.iter().filter(|(name, ..)| name.len() <= 255).collect();
+    symbols.sort_by_key(|(_name, address, _size)| *address);
+    let mut contents = vec![];
+    for (name, address, size) in symbols {
+        contents.push(name.len() as u8);
+        contents.extend_from_slice(name.as_bytes());
+        contents.extend_from_slice(&(*address as u32).to_be_bytes());
+        contents.extend_from_slice(&(*size as u32).to_be_bytes());
+    }
+    contents
+}
+
+/// Validates an image and returns the name, address, and size of every variable in its `SYMB`
+/// section. The list is empty if the image has no symbol table.
+pub fn read_symbols(image: &[u8]) -> Result<Vec<(String, usize, usize)>, TransientError> {
+    let mut symbols = vec![];
+    for mut contents in find_sections(image, SYMBOL_MARKER)? {
+        while let Some((length, rest)) = contents.split_first() {
+            let entry = rest.get(..*length as usize + 8).ok_or(TransientError::InvalidImageFormat("Truncated symbol"))?;
+            let (name, fields) = entry.split_at(*length as usize);
+            let address = fields[0..4].iter().fold(0usize, |value, byte| value << 8 | *byte as usize);
+            let size = fields[4..8].iter().fold(0usize, |value, byte| value << 8 | *byte as usize);
+            symbols.push((String::from_utf8_lossy(name).into_owned(), address, size));
+            contents = &rest[entry.len()..];
+        }
+    }
+    Ok(symbols)
+}
+
+/// Computes the 32 bit FNV-1a hash of a source file, which identifies it in the `DBUG` section
+pub fn file_hash(source_code: &[u8]) -> u32 {
+    source_code.iter().fold(0x811C9DC5u32, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x01000193))
+}
+
+/// Computes the CRC-16/CCITT-FALSE checksum of `data`
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
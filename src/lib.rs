@@ -0,0 +1,22 @@
+//! Toolchain for TransientAssembly: the TIR compiler, the transient virtual machine, and the
+//! types they share.
+//!
+//! Without the default `std` feature the crate is `no_std` and only provides the virtual machine and
+//! the image format. Programs then reach the host through `vm::TransientIO`. The transient memory
+//! is an array inside the processor, but the crate still depends on `alloc` for snapshots and
+//! symbols, so the target needs a global allocator.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod compiler;
+pub mod error;
+pub mod image;
+pub mod vm;
+
+#[cfg(feature = "std")]
+pub use compiler::{compile, Operation};
+pub use error::{CompileError, TransientError};
+pub use vm::{TransientMode, TransientIO, TransientSnapshot, TransientState};
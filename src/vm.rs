@@ -0,0 +1,4658 @@
+//! Transient is, in essence, a custom virtual machine and file format. The transient processor
+//! loads a transient "image' into the virtual address space and begins execution at offset 0x00.
+//!
+//!
+//! # Opcodes
+//! - 0x00: NOP does nothing
+//! - 0x01: MOV byte from source1 into destination
+//! - 0x02: ADD source1 and source2 and store result in destination
+//! - 0x03: SUB source2 from source1 and store result in destination
+//! - 0x04: MUL source1 and source2 and store result in destination
+//! - 0x05: DIV source1 by source2 and store result in destination (truncated)
+//! - 0x06: DIV source1 by source2 and store result in destination (rounded)
+//! - 0x07: REM divides source1 by source2 and stores the remainder in destination
+//! - 0x08: CGT compare if source1 is greater than source2, and if so, store 1 in destination
+//! - 0x09: CLT compare if source1 is less than source2, and if so, store 1 in destination
+//! - 0x0A: JMP stops current execution and jumps to code in source1
+//! - 0x0B: JIE stops current execution and jumps to code in source1 ONLY IF source2 is non-zero
+//! - 0x0C: JNE stops current execution and jumps to code in source1 ONLY IF source2 is zero
+//! - 0x0D: PUT prints data at source1 to the screen (int)
+//! - 0x0E: PUT prints data at source1 to the screen (char)
+//! - 0x0F: IMZ gets the image size that was loaded to ROM and stores it in destination
+//! - 0x10: EQU compare if source1 and source2 are equal, and if so, store 1 in destination
+//! - 0x11: AND bitwise and source1 with source2 and store the result in destination
+//! - 0x12: OR bitwise or source1 with source2 and store the result in destination
+//! - 0x13: XOR bitwise xor source1 with source2 and store the result in destination
+//! - 0x14: NOT invert every bit of source1 and store the result in destination
+//! - 0x15: SHL shift source1 left by source2 bits and store the result in destination
+//! - 0x16: SHR shift source1 right by source2 bits and store the result in destination
+//! - 0x17: PUSH push source1 onto the stack
+//! - 0x18: POP pop the top of the stack into destination
+//! - 0x19: CALL pushes the address of the next instruction onto the stack and jumps to target
+//! - 0x1A: RET pops an address from the stack and jumps to it
+//! - 0x1B: CGE compare if source1 is greater than or equal to source2, and if so, store 1 in destination
+//! - 0x1C: CLE compare if source1 is less than or equal to source2, and if so, store 1 in destination
+//! - 0x1D: GET_I reads an integer from the input and stores it in destination
+//! - 0x1E: GET_C reads a character from the input and stores it in destination
+//! - 0x1F: PUT_S prints the null-terminated string starting at source1
+//! - 0x20: MEMCPY copies a region of memory between the addresses held by source1 and source2
+//! - 0x21: MEMSET fills the region of memory at the address held by source1 with a byte
+//! - 0x22: SWAP exchanges the values of source1 and source2
+//! - 0x23: BSWAP reverses the byte order of source1 and stores the result in destination
+//! - 0x24: POPCNT counts the set bits of source1 and stores the count in destination
+//! - 0x25: CLZ counts the leading zero bits of source1 and stores the count in destination
+//! - 0x26: CTZ counts the trailing zero bits of source1 and stores the count in destination
+//! - 0x27: ROTL rotate source1 left by source2 bits and store the result in destination
+//! - 0x28: ROTR rotate source1 right by source2 bits and store the result in destination
+//! - 0x29: INC adds 1 to source1 in place
+//! - 0x2A: DEC subtracts 1 from source1 in place
+//! - 0x2B: NEG negate source1 and store the result in destination
+//! - 0x2C: ABS store the absolute value of signed source1 in destination
+//! - 0x2D: ASSERT halts with an error if source1 is zero
+//! - 0x2E: NASSERT halts with an error if source1 is non-zero
+//! - 0x2F: TRAP pauses the processor if an instruction hook is registered
+//! - 0x30: BOUNDS checks that source1 lies between source2 and source3 and stores the result in destination
+//! - 0x31: SCGT compare if signed source1 is greater than signed source2, and if so, store 1 in destination
+//! - 0x32: SCLT compare if signed source1 is less than signed source2, and if so, store 1 in destination
+//! - 0x33: SEQU compare if signed source1 and signed source2 are equal, and if so, store 1 in destination
+//! - 0x34: ADDO adds source1 and source2 into destination and flags whether the result wrapped
+//! - 0x35: SUBO subtracts source2 from source1 into destination and flags whether the result wrapped
+//! - 0x36: MULO multiplies source1 and source2 into destination and flags whether the result wrapped
+//! - 0x37: SADD adds source1 and source2, saturating at the bounds of the size, and stores result in destination
+//! - 0x38: SSUB subtracts source2 from source1, saturating at the bounds of the size, and stores result in destination
+//! - 0x39: SMUL multiplies source1 and source2, saturating at the bounds of the size, and stores result in destination
+//! - 0x3A: MIN stores the smaller of source1 and source2 in destination
+//! - 0x3B: MAX stores the larger of source1 and source2 in destination
+//! - 0x3C: MOD divides signed source1 by signed source2 and stores the non-negative remainder in destination
+//! - 0x3D: IMM stores the value embedded in the instruction in destination
+//! - 0x3E: RANDOM stores a pseudorandom number in destination
+//! - 0x3F: CLOCK stores the nanoseconds elapsed since the processor was created in destination
+//! - 0x40: PUT_H prints source1 in hexadecimal
+//! - 0x41: STRLEN stores the length of the null-terminated string at source in destination
+//! - 0x42: STRCMP compares the null-terminated strings at source1 and source2 and stores -1, 0, or 1 in destination
+//! - 0x91: MEMMOVE copies a possibly overlapping region of memory from source1 to destination
+//! - 0x92: PRTMM prints every symbol of the loaded image and its current value to stderr
+//! - 0x93: SYSCALL invokes a host operation from the fixed dispatch table (see below)
+//! - 0x94: SAVEFP stores the frame pointer in destination
+//! - 0x95: RESTFP loads the frame pointer from source1
+//! - 0x96: SWITCH jumps to the target of the table entry matching source1, or to a default
+//! - 0x97: COSAVE stores the execution context (program counter and stack pointer) in destination
+//! - 0x98: CORESTORE loads the execution context from source1 and resumes it
+//! - 0x99: PROFREGION marks the entry into a profiling region
+//! - 0x9A: REPEAT executes the next instruction as many times as source1 holds, advancing the
+//!   addresses of a sized instruction by its size after every time
+//! - 0x9B: SETPRED loads the condition register from the least significant byte of source1
+//! - 0x9C: CLRPRED sets the condition register to 1
+//! - 0x9D: ADDMOD adds source1 and source2 modulo source3 and stores result in destination
+//! - 0x9E: MULMOD multiplies source1 and source2 modulo source3 and stores result in destination
+//! - 0x9F: MODPOW raises source1 to the power of source2 modulo source3 and stores result in destination
+//! - 0xA0: INVMOD stores the inverse of source1 modulo source2 in destination, if it exists
+//! - 0xA1: XORSHIFT64 advances the xorshift64 state in source1 and stores it in destination
+//! - 0xA2: CHACHA20BLK computes the ChaCha20 block of the state at source1 into destination
+//! - 0xA3: SHA256COMP applies the SHA-256 compression function of the block at source2 to the state at source1
+//! - 0xA4: POPSTACK discards as many bytes from the top of the stack as source1 holds
+//! - 0xA5: PEEK copies the top of the stack into destination without popping it
+//! - 0xA6: PUSHPC pushes the address of the next instruction onto the stack
+//! - 0xA7: POPPC pops an address from the stack and jumps to it
+//! - 0xA8: VCMPEQ8 compares two byte arrays for equality element-wise
+//! - 0xA9: VCMPGT8 compares if the bytes of one array are greater than another element-wise
+//! - 0xAA: DOTPROD computes the dot product of two byte arrays and stores it in destination
+//! - 0xAB: TLOOKUP copies the table element at the index in source1 into destination
+//! - 0xAC: SETOUT redirects output to the device with the id in source1 (0 is stdout)
+//! - 0xAD: SETIN redirects input to the device with the id in source1 (0 is stdin)
+//! - 0xAE: BLOOPN runs the loop body at source2 the number of times in source1
+//! - 0xAF: LEND ends a BLOOPN loop body
+//! - 0xB0: RFIND stores the offset of the last byte equal to source3 in the region at source1 into destination
+//! - 0xB1: SPLITB unpacks the value in source1 into consecutive bytes starting at destination
+//! - 0xB2: JOINB packs the consecutive bytes starting at source1 into destination
+//! - 0xB3: INTERLEAVE alternates the bytes of the arrays at source1 and source2 into destination
+//! - 0xB4: DEINTERLEAVE separates the alternating bytes of the array at source1 into two arrays
+//! - 0xB5: RLEENC run-length encodes the region at source1 into (count, value) byte pairs
+//! - 0xB6: RLEDEC expands the (count, value) byte pairs at source1 into the region at destination
+//! - 0xB7: DELTAENC writes the byte differences of the region at source1 into destination
+//! - 0xB8: DELTADEC reconstructs the bytes of the difference sequence at source1 into destination
+//! - 0xB9: NIBPK packs pairs of 4-bit values from the array at source1 into single bytes at destination
+//! - 0xBA: NIBUPK unpacks the 4-bit values packed at source1 into separate bytes at destination
+//! - 0xBB: FIBSTEP advances the Fibonacci pair in source1 and source2 by one step
+//! - 0xBC: BSEARCH binary searches the sorted byte array at source1 for source3 and stores the index in destination
+//! - 0xBD: QSORT sorts the array of elements at source1 in lexicographic byte order
+//! - 0xBE: UNIQSORT sorts and deduplicates the byte array at source1 in place
+//! - 0xBF: PCTILE stores the value at the percentile in source3 of the byte array at source1 into destination
+//! - 0xC0: HISTO counts the occurrences of every byte value in the region at source1 into destination
+//! - 0xC1: WSAMPLE stores the index of a random sample from the weights at source1 into destination
+//! - 0xC2: BITMATMUL multiplies the bit matrices at source1 and source2 over GF(2) into destination
+//! - 0xC3: POPCNTA counts the set bits in the region at source1 into destination
+//! - 0xC4: DITHER dithers the grayscale image at source1 to black and white into destination
+//! - 0xFE: HLTE halts execution and stops processor with the exit code in source1
+//! - 0xFF: HLT halts execution and stops processor
+//!
+//! # Instruction format
+//! Most instructions with a size byte, and HLT, are 14 bytes long: the opcode, a size byte holding
+//! the operand size in bytes, and the 4 byte big-endian addresses of source1, source2, and
+//! destination. The layout of every instruction is listed below. Every other address operand is 4
+//! bytes wide as well. Values in memory are big-endian.
+//!
+//! # Delay slots
+//! When bit 0x40 of the second byte of a JMP, JIE, or JNE is set, the instruction directly after
+//! the jump is executed before the jump takes effect, whether or not the jump is taken. The delay
+//! slot can't hold another jump, a call, a return, or a halt.
+//!
+//! # Predicated execution
+//! When bit 0x80 of the second byte of an instruction with a size byte is set, the instruction is
+//! skipped unless the condition register is non-zero.
+//!
+//! # Transient addresses
+//! Source1, source2, and destination are transient addresses. These can range from 0 up to TRANSIENT_MEM_MAX. Do note, however, that the transient processor will
+//! fill the transient memory with program data up to the programs length. To get the length of the
+//! program, see opcodes above.
+//!
+//! # System calls
+//! SYSCALL reads its arguments as 32 bit values from the argument registers at 0x00, 0x04, 0x08,
+//! and 0x0C, and writes its result back to 0x00. Failed calls return 0xFFFFFFFF.
+//! - 0: exit(code) halts the processor with the exit code, like HLTE
+//! - 1: write(fd, buf, len) writes len bytes at buf to the output device (1), stderr (2), or an opened file
+//! - 2: read(fd, buf, len) reads up to len bytes into buf from the input device (0) or an opened file
+//! - 3: open(path, flags) opens the null-terminated path for reading (0), writing (1), or appending (2)
+//! - 4: close(fd) closes an opened file
+//!
+//! # Without `std`
+//! With the `std` feature disabled, the processor writes its output and reads its input through
+//! the `TransientIO` given to `TransientState::new_with_io`, and drops output and reads no input
+//! without one. Files, stderr, registered devices, and CLOCK are unavailable: SYSCALL 3 and 4 and
+//! writes to fd 2 fail, SETOUT and SETIN only accept device 0, PRTMM prints to the output, CLOCK
+//! raises an error, and RANDOM starts from a fixed seed.
+//!
+//! The transient memory is an array inside `TransientState`, so it lives wherever the processor
+//! does, and a processor with a large TRANSIENT_MEM_MAX needs a large stack or a static. Snapshots,
+//! symbols, and the rest of the bookkeeping are still allocated through `alloc`, so a `no_std`
+//! target needs a global allocator.
+
+/*
+Nop
+Layout: opcode
+Opcode: 0x00
+Description: Does nothing. Zeroed memory decodes as a sequence of NOPs
+
+Mov
+Layout: opcode size src1[4] unused[4] dest[4]
+Opcode: 0x01
+Description: Copies src1 to dest
+
+Add
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x02
+Description: Adds src1 and src2 and stores in dest
+
+Sub
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x03
+Description: Subtracts src2 from src1 and stores in dest
+
+Mul
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x04
+Description: Multiplies src1 and src2 and stores in dest
+
+Divt
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x05
+Description: Divides src1 by src2 and stores the truncated quotient in dest
+
+Divr
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x06
+Description: Divides src1 by src2 and stores the rounded quotient in dest
+
+Rem
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x07
+Description: Divides src1 by src2 and stores remainder in dest
+
+Cgt
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x08
+Description: If src1 is greater than src2, store 0x1 in dest, otherwise store 0x0
+
+Clt
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x09
+Description: If src1 is less than src2, store 0x1 in dest, otherwise store 0x0
+
+Jmp
+Layout: opcode size target[4] unused[4] unused[4]
+Opcode: 0x0A
+Description: Set program counter to target, effectively jumping to target
+
+Jie
+Layout: opcode size target[4] src2[4] unused[4]
+Opcode: 0x0B
+Description: Set program counter to target if src2 is not 0x0
+
+Jne
+Layout: opcode size target[4] src2[4] unused[4]
+Opcode: 0x0C
+Description: Set program counter to target if src2 is 0x0
+
+PutI
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0x0D
+Description: Print src1 to the active output device as an integer
+
+PutC
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0x0E
+Description: Print src1 to the active output device as an ascii character
+
+Imz
+Layout: opcode size unused[4] unused[4] dest[4]
+Opcode: 0x0F
+Description: Invokes the image size (in bytes) from the virtual machine and stores it in dest
+
+Equ
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x10
+Description: If src1 is equal to src2, store 0x1 in dest, otherwise store 0x0
+
+And
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x11
+Description: Stores the bitwise AND of src1 and src2 in dest
+
+Or
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x12
+Description: Stores the bitwise OR of src1 and src2 in dest
+
+Xor
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x13
+Description: Stores the bitwise XOR of src1 and src2 in dest
+
+Not
+Layout: opcode size src1[4] unused[4] dest[4]
+Opcode: 0x14
+Description: Stores the bitwise complement of src1 in dest
+
+Shl
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x15
+Description: Shifts src1 left by the number of bits stored in src2 and stores the result in dest. Only the low 6 bits of the shift amount are used
+
+Shr
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x16
+Description: Shifts src1 right by the number of bits stored in src2 and stores the result in dest. Only the low 6 bits of the shift amount are used
+
+Push
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0x17
+Description: Decrements the stack pointer by size and writes src1 to the new top of the stack
+
+Pop
+Layout: opcode size unused[4] unused[4] dest[4]
+Opcode: 0x18
+Description: Reads size bytes from the top of the stack into dest and increments the stack pointer by size
+
+Call
+Layout: opcode target[4]
+Opcode: 0x19
+Description: Pushes the 4 byte address of the next instruction onto the stack and sets program counter to target
+
+Ret
+Layout: opcode
+Opcode: 0x1A
+Description: Pops a 4 byte address from the stack and sets program counter to it
+
+Cge
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x1B
+Description: If src1 is greater than or equal to src2, store 0x1 in dest, otherwise store 0x0
+
+Cle
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x1C
+Description: If src1 is less than or equal to src2, store 0x1 in dest, otherwise store 0x0
+
+GetI
+Layout: opcode size unused[4] unused[4] dest[4]
+Opcode: 0x1D
+Description: Reads a whitespace-delimited decimal integer from the active input device and stores it in dest
+
+GetC
+Layout: opcode size unused[4] unused[4] dest[4]
+Opcode: 0x1E
+Description: Reads a single byte from the active input device and stores it in dest
+
+PutS
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0x1F
+Description: Prints the bytes starting at src1 up to the first 0x0 to the active output device
+
+Memcpy
+Layout: opcode dest_ptr[4] src_ptr[4] len_ptr[4]
+Opcode: 0x20
+Description: Copies the number of bytes stored at len_ptr from the address stored at src_ptr to the address
+stored at dest_ptr. The regions may overlap
+
+Memset
+Layout: opcode dest_ptr[4] val_ptr[4] len_ptr[4]
+Opcode: 0x21
+Description: Sets the number of bytes stored at len_ptr, starting at the address stored at dest_ptr, to the
+byte stored at val_ptr
+
+Swap
+Layout: opcode size src1[4] src2[4] unused[4]
+Opcode: 0x22
+Description: Exchanges the values stored at src1 and src2
+
+Bswap
+Layout: opcode size src1[4] unused[4] dest[4]
+Opcode: 0x23
+Description: Reverses the order of the size bytes of src1 and stores the result in dest, converting between big and little endian
+
+Popcnt
+Layout: opcode size src1[4] unused[4] dest[4]
+Opcode: 0x24
+Description: Stores the number of set bits in src1 in dest
+
+Clz
+Layout: opcode size src1[4] unused[4] dest[4]
+Opcode: 0x25
+Description: Stores the number of zero bits above the highest set bit of src1 in dest. A value of 0x0 has size * 8 leading zeros
+
+Ctz
+Layout: opcode size src1[4] unused[4] dest[4]
+Opcode: 0x26
+Description: Stores the number of zero bits below the lowest set bit of src1 in dest. A value of 0x0 has size * 8 trailing zeros
+
+Rotl
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x27
+Description: Rotates src1 left by the number of bits stored in src2 within the operand size and stores the result in dest. Only the low 6 bits of the rotation amount are used
+
+Rotr
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x28
+Description: Rotates src1 right by the number of bits stored in src2 within the operand size and stores the result in dest. Only the low 6 bits of the rotation amount are used
+
+Inc
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0x29
+Description: Adds 0x1 to src1 and stores the result back in src1
+
+Dec
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0x2A
+Description: Subtracts 0x1 from src1 and stores the result back in src1
+
+Neg
+Layout: opcode size src1[4] unused[4] dest[4]
+Opcode: 0x2B
+Description: Stores the two's complement negation of src1 in dest
+
+Abs
+Layout: opcode size src1[4] unused[4] dest[4]
+Opcode: 0x2C
+Description: Interprets src1 as a signed two's complement integer of the operand size and stores its absolute value in dest. Fails if src1 is the smallest signed value, which has no positive counterpart
+
+Assert
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0x2D
+Description: Halts the processor with an assertion failure if src1 is 0x0. Skipped unless debug assertions are enabled
+
+Nassert
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0x2E
+Description: Halts the processor with an assertion failure if src1 is not 0x0. Skipped unless debug assertions are enabled
+
+Trap
+Layout: opcode
+Opcode: 0x2F
+Description: Pauses the processor after this instruction if an instruction hook is registered. The hook has already been called with the TRAP and its address at that point. Without a hook, TRAP does nothing
+
+Bounds
+Layout: opcode size value[4] low[4] high[4] dest[4]
+Opcode: 0x30
+Description: If value is at least low and at most high, store 0x1 in dest, otherwise store 0x0
+
+Scgt
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x31
+Description: Interprets src1 and src2 as signed two's complement integers of the operand size. If src1 is greater than src2, store 0x1 in dest, otherwise store 0x0
+
+Sclt
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x32
+Description: Interprets src1 and src2 as signed two's complement integers of the operand size. If src1 is less than src2, store 0x1 in dest, otherwise store 0x0
+
+Sequ
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x33
+Description: Interprets src1 and src2 as signed two's complement integers of the operand size. If src1 is equal to src2, store 0x1 in dest, otherwise store 0x0. Equality doesn't depend on the sign, so this behaves like EQU
+
+Addo
+Layout: opcode size src1[4] src2[4] dest[4] overflow[4]
+Opcode: 0x34
+Description: Adds src1 and src2 and stores the wrapped result in dest. If the sum doesn't fit in the operand size, store 0x1 in overflow, otherwise store 0x0
+
+Subo
+Layout: opcode size src1[4] src2[4] dest[4] overflow[4]
+Opcode: 0x35
+Description: Subtracts src2 from src1 and stores the wrapped result in dest. If src2 is greater than src1, store 0x1 in overflow, otherwise store 0x0
+
+Mulo
+Layout: opcode size src1[4] src2[4] dest[4] overflow[4]
+Opcode: 0x36
+Description: Multiplies src1 and src2 and stores the wrapped result in dest. If the product doesn't fit in the operand size, store 0x1 in overflow, otherwise store 0x0
+
+Sadd
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x37
+Description: Adds src1 and src2 and stores the sum in dest, clamped to the largest or smallest value of the operand size instead of wrapping. The operands are signed if signed arithmetic is enabled
+
+Ssub
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x38
+Description: Subtracts src2 from src1 and stores the difference in dest, clamped to the largest or smallest value of the operand size instead of wrapping. The operands are signed if signed arithmetic is enabled
+
+Smul
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x39
+Description: Multiplies src1 and src2 and stores the product in dest, clamped to the largest or smallest value of the operand size instead of wrapping. The operands are signed if signed arithmetic is enabled
+
+Min
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x3A
+Description: Stores the smaller of src1 and src2 in dest. The operands are signed if signed arithmetic is enabled
+
+Max
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x3B
+Description: Stores the larger of src1 and src2 in dest. The operands are signed if signed arithmetic is enabled
+
+Mod
+Layout: opcode size src1[4] src2[4] dest[4]
+Opcode: 0x3C
+Description: Treats src1 and src2 as signed numbers of the operand size and stores the true modulo of src1 by src2 in dest. Unlike REM, the result is never negative. Halts with a division by zero if src2 is zero
+
+Imm
+Layout: opcode size value[size] dest[4]
+Opcode: 0x3D
+Description: Stores value in dest. The value is part of the instruction rather than a variable, so the instruction is 2 + size + 4 bytes long
+
+Rand
+Layout: opcode size unused[4] unused[4] dest[4]
+Opcode: 0x3E
+Description: Stores a pseudorandom number of the operand size in dest. The generator is seeded from the system time when the processor is created, or with TransientState::set_rng_seed
+
+Clock
+Layout: opcode size unused[4] unused[4] dest[4]
+Opcode: 0x3F
+Description: Stores the number of nanoseconds elapsed since the processor was created in dest, truncated to the operand size. This is a monotonic clock, not the time of day
+
+PutH
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0x40
+Description: Prints src1 in hexadecimal with a 0x prefix and leading zeros to fill two digits per byte, e.g. 0x0000001F for a size of 4
+
+Strlen
+Layout: opcode src[4] dest[4]
+Opcode: 0x41
+Description: Counts the bytes of the null-terminated string at src, excluding the terminator, and stores the count in dest as a 32 bit value
+
+Strcmp
+Layout: opcode src1[4] src2[4] dest[4]
+Opcode: 0x42
+Description: Compares the null-terminated strings at src1 and src2 byte by byte and stores 0xFF (-1) in dest if the first string sorts before the second, 0 if they are equal, and 1 if it sorts after. The result is 1 byte
+
+Memmove
+Layout: opcode len_addr[4] src_base[4] dst_base[4]
+Opcode: 0x91
+Description: Copies the number of bytes stored at len_addr from src_base to dst_base. The regions may overlap
+
+Prtmm
+Layout: opcode
+Opcode: 0x92
+Description: Prints the name, address, size, and current value of every variable in the symbol section of the loaded image. Text is
+printed as a string and values longer than 8 bytes as a hex dump, so PRTMM never halts the processor
+
+Syscall
+Layout: opcode syscall_id[4]
+Opcode: 0x93
+Description: Invokes the host operation syscall_id with the arguments in the argument registers
+
+Savefp
+Layout: opcode dest_addr[4]
+Opcode: 0x94
+Description: Stores the frame pointer as a 32 bit value in dest_addr
+
+Restfp
+Layout: opcode src_addr[4]
+Opcode: 0x95
+Description: Loads the frame pointer from the 32 bit value in src_addr
+
+Switch
+Layout: opcode val_addr[4] table_base[4] table_len_addr[4] default_addr[4]
+Opcode: 0x96
+Description: Binary searches a table of table_len entries, each an 8 byte value followed by a 4 byte
+target, sorted by value. Jumps to the target whose value matches val_addr, or to default_addr
+
+Cosave
+Layout: opcode ctx_addr[4]
+Opcode: 0x97
+Description: Stores the address of the next instruction and the stack pointer as two consecutive 32 bit
+values at ctx_addr
+
+Corestore
+Layout: opcode ctx_addr[4]
+Opcode: 0x98
+Description: Loads the program counter and stack pointer saved by COSAVE from ctx_addr, resuming execution
+after the COSAVE
+
+Profregion
+Layout: opcode region_id[4]
+Opcode: 0x99
+Description: Attributes the following instructions to region_id when profiling is enabled
+
+Repeat
+Layout: opcode count_addr[4]
+Opcode: 0x9A
+Description: Executes the instruction that follows as many times as count_addr holds, then continues after it. Every
+iteration is hooked and counted as an instruction of its own. Jumps, calls, returns, and halts can't be repeated
+
+Setpred
+Layout: opcode val_addr[4]
+Opcode: 0x9B
+Description: Loads the least significant byte of val_addr into the condition register
+
+Clrpred
+Layout: opcode
+Opcode: 0x9C
+Description: Sets the condition register to 1, so predicated instructions always execute
+
+Addmod
+Layout: opcode size arg_1[4] arg_2[4] mod_addr[4] arg_3[4]
+Opcode: 0x9D
+Description: Computes (arg_1 + arg_2) % mod_addr without intermediate overflow and stores in arg_3
+
+Mulmod
+Layout: opcode size arg_1[4] arg_2[4] mod_addr[4] arg_3[4]
+Opcode: 0x9E
+Description: Computes (arg_1 * arg_2) % mod_addr without intermediate overflow and stores in arg_3
+
+Modpow
+Layout: opcode size base_addr[4] exp_addr[4] mod_addr[4] dest_addr[4]
+Opcode: 0x9F
+Description: Computes base_addr ^ exp_addr % mod_addr and stores in dest_addr. The modulus must be at least 2
+
+Invmod
+Layout: opcode size val_addr[4] mod_addr[4] dest_addr[4] ok_addr[4]
+Opcode: 0xA0
+Description: Stores the modular multiplicative inverse of val_addr modulo mod_addr in dest_addr. Stores 0x1 in
+ok_addr if the inverse exists, otherwise store 0x0
+
+Xorshift64
+Layout: opcode state_addr[4] dest_addr[4]
+Opcode: 0xA1
+Description: Applies one xorshift64 step to the non-zero 8 byte state in state_addr, and stores the new state in
+both state_addr and dest_addr
+
+Chacha20blk
+Layout: opcode state_base[4] out_base[4]
+Opcode: 0xA2
+Description: Reads a 64 byte ChaCha20 state (16 little endian words) from state_base, applies the 20 round block
+function, and writes the 64 bytes of keystream to out_base
+
+Sha256comp
+Layout: opcode state_base[4] block_base[4]
+Opcode: 0xA3
+Description: Reads a 32 byte SHA-256 state (8 big endian words) from state_base and a 64 byte message block from
+block_base, applies the SHA-256 compression function, and writes the updated state back to state_base
+
+Popstack
+Layout: opcode count_addr[4]
+Opcode: 0xA4
+Description: Advances the stack pointer by the number of bytes in count_addr, discarding the top of the stack
+
+Peek
+Layout: opcode size dest_addr[4]
+Opcode: 0xA5
+Description: Copies size bytes from the top of the stack to dest_addr without changing the stack pointer
+
+Pushpc
+Layout: opcode
+Opcode: 0xA6
+Description: Pushes the address of the next instruction onto the stack as a 32 bit value
+
+Poppc
+Layout: opcode
+Opcode: 0xA7
+Description: Pops a 32 bit address from the stack and sets the program counter to it
+
+Vcmpeq8
+Layout: opcode src1_base[4] src2_base[4] count_addr[4] dest_base[4]
+Opcode: 0xA8
+Description: For each of the count_addr bytes, store 0x1 in dest_base if src1_base and src2_base are equal,
+otherwise store 0x0
+
+Vcmpgt8
+Layout: opcode src1_base[4] src2_base[4] count_addr[4] dest_base[4]
+Opcode: 0xA9
+Description: For each of the count_addr bytes, store 0x1 in dest_base if src1_base is greater than src2_base,
+otherwise store 0x0
+
+Dotprod
+Layout: opcode len_addr[4] src1_base[4] src2_base[4] dest_addr[4]
+Opcode: 0xAA
+Description: Computes the sum of src1_base[i] * src2_base[i] over the len_addr bytes of both arrays and stores
+the 8 byte result in dest_addr
+
+Tlookup
+Layout: opcode index_addr[4] table_base[4] elem_size dest_addr[4]
+Opcode: 0xAB
+Description: Copies the elem_size bytes at table_base + index_addr * elem_size to dest_addr
+
+Setout
+Layout: opcode device_id_addr[4]
+Opcode: 0xAC
+Description: Sends all following output to the output device registered with the id in device_id_addr
+
+Setin
+Layout: opcode device_id_addr[4]
+Opcode: 0xAD
+Description: Reads all following input from the input device registered with the id in device_id_addr
+
+Bloopn
+Layout: opcode count_addr[4] body_start_addr[4]
+Opcode: 0xAE
+Description: Executes the loop body starting at body_start_addr count times, then continues after the BLOOPN. The body must end with LEND
+
+Lend
+Layout: opcode
+Opcode: 0xAF
+Description: Ends a BLOOPN loop body. Jumps back to the body start if iterations remain, otherwise continues after the BLOOPN
+
+Rfind
+Layout: opcode base_addr[4] len_addr[4] val_addr[4] result_addr[4]
+Opcode: 0xB0
+Description: Scans memory[base..base+len] backward and stores the offset of the last byte equal to the byte in val_addr into result_addr as a 64 bit value, or len if no byte matches
+
+Splitb
+Layout: opcode size src_addr[4] dest_base[4]
+Opcode: 0xB1
+Description: Reads size bytes from src_addr and writes each byte to dest_base, dest_base+1, ..., dest_base+size-1 in big-endian order
+
+Joinb
+Layout: opcode size src_base[4] dest_addr[4]
+Opcode: 0xB2
+Description: Reads size bytes from src_base, src_base+1, ..., src_base+size-1 and writes them to dest_addr as a single big-endian value of size bytes
+
+Interleave
+Layout: opcode ch1_base[4] ch2_base[4] count_addr[4] dest_base[4]
+Opcode: 0xB3
+Description: Writes count bytes from each of ch1_base and ch2_base alternately to dest_base as ch1[0], ch2[0], ch1[1], ch2[1], ...
+
+Deinterleave
+Layout: opcode src_base[4] count_addr[4] ch1_dest[4] ch2_dest[4]
+Opcode: 0xB4
+Description: Reads count byte pairs from src_base and writes the first byte of each pair to ch1_dest and the second to ch2_dest
+
+Rleenc
+Layout: opcode src_base[4] src_len_addr[4] dest_base[4] dest_cap_addr[4] out_len_dest[4]
+Opcode: 0xB5
+Description: Encodes memory[src_base..src_base+src_len] as (count, value) byte pairs at dest_base, splitting runs longer than 255 into several pairs, and stores the encoded length in out_len_dest. Halts if the output exceeds dest_cap bytes
+
+Rledec
+Layout: opcode src_base[4] src_len_addr[4] dest_base[4] dest_cap_addr[4] out_len_dest[4]
+Opcode: 0xB6
+Description: Expands the (count, value) byte pairs in memory[src_base..src_base+src_len] to dest_base and stores the decoded length in out_len_dest. Halts if the output exceeds dest_cap bytes or the input ends in the middle of a pair
+
+Deltaenc
+Layout: opcode src_base[4] src_len_addr[4] dest_base[4]
+Opcode: 0xB7
+Description: Writes src[0] followed by src[i] - src[i-1] (wrapping) for every following byte of memory[src_base..src_base+src_len] to dest_base
+
+Deltadec
+Layout: opcode src_base[4] src_len_addr[4] dest_base[4]
+Opcode: 0xB8
+Description: Reconstructs the original bytes from the difference sequence in memory[src_base..src_base+src_len] produced by DELTAENC and writes them to dest_base
+
+Nibpk
+Layout: opcode src_base[4] count_addr[4] dest_base[4]
+Opcode: 0xB9
+Description: Reads count bytes from src_base as 4-bit values and packs each pair into one byte at dest_base with the first value in the high nibble. An odd final value is packed with a zero low nibble
+
+Nibupk
+Layout: opcode src_base[4] count_addr[4] dest_base[4]
+Opcode: 0xBA
+Description: Unpacks count 4-bit values from the bytes at src_base, high nibble first, and writes each to its own byte at dest_base
+
+Fibstep
+Layout: opcode a_addr[4] b_addr[4]
+Opcode: 0xBB
+Description: Treats a_addr and b_addr as 64 bit values and advances them to the next Fibonacci pair: a = old b, b = old a + old b (wrapping)
+
+Bsearch
+Layout: opcode arr_base[4] count_addr[4] target_addr[4] result_idx_addr[4]
+Opcode: 0xBC
+Description: Binary searches the sorted array of count bytes at arr_base for the byte in target_addr and stores the index of a matching element in result_idx_addr as a 64 bit value. If there is no match the bitwise complement of the insertion point is stored instead
+
+Qsort
+Layout: opcode base_addr[4] count_addr[4] elem_size_addr[4] descending_flag_addr[4]
+Opcode: 0xBD
+Description: Sorts the array of count elements of elem_size bytes each at base_addr in lexicographic byte order. The order is descending if the value in descending_flag_addr is not 0
+
+Uniqsort
+Layout: opcode base_addr[4] count_addr[4] out_count_dest[4]
+Opcode: 0xBE
+Description: Sorts the array of count bytes at base_addr, compacts it in place so every byte value appears once, and stores the number of unique bytes in out_count_dest as a 64 bit value
+
+Pctile
+Layout: opcode arr_base[4] count_addr[4] percentile_addr[4] dest_addr[4]
+Opcode: 0xBF
+Description: Sorts a copy of the array of count bytes at arr_base and stores the value at the percentile (0-100) in percentile_addr into dest_addr as a 64 bit value, using the nearest-rank method. The array itself is not modified
+
+Histo
+Layout: opcode src_base[4] src_len_addr[4] dest_base[4]
+Opcode: 0xC0
+Description: Counts the occurrences of each byte value 0x00-0xFF in memory[src_base..src_base+src_len] and stores them as 256 consecutive 16 bit big-endian counts starting at dest_base, which needs 512 bytes
+
+Wsample
+Layout: opcode weights_base[4] count_addr[4] rng_state_addr[4] result_addr[4]
+Opcode: 0xC1
+Description: Advances the xorshift64 state in rng_state_addr and uses it to pick an index into the array of count byte weights at weights_base, with each index chosen in proportion to its weight. The index is stored in result_addr as a 64 bit value
+
+Bitmatmul
+Layout: opcode rows_addr[4] cols_addr[4] shared_addr[4] mat1_base[4] mat2_base[4] dest_base[4]
+Opcode: 0xC2
+Description: Multiplies the rows x shared bit matrix at mat1_base by the shared x cols bit matrix at mat2_base over GF(2), with AND as multiplication and XOR as addition, and stores the rows x cols result at dest_base. Each matrix row is a 64 bit value with column n in bit n, so every dimension is at most 64
+
+Popcnta
+Layout: opcode base_addr[4] len_addr[4] dest_addr[4]
+Opcode: 0xC3
+Description: Counts the set bits in memory[base..base+len] and stores the total in dest_addr as a 32 bit value
+
+Dither
+Layout: opcode src_base[4] width_addr[4] height_addr[4] dest_base[4]
+Opcode: 0xC4
+Description: Quantizes every pixel byte of the row-major width x height grayscale image at src_base to 0 or 255 with Floyd-Steinberg error diffusion, passing 7/16 of the error to the right, 3/16 to the bottom-left, 5/16 to the bottom, and 1/16 to the bottom-right neighbour. The result is written to dest_base
+
+Hlt
+Layout: opcode size unused[4] unused[4] unused[4]
+Opcode: 0xFF
+Description: Halts execution and exits the virtual machine
+
+Hlte
+Layout: opcode size src1[4] unused[4] unused[4]
+Opcode: 0xFE
+Description: Halts execution and exits the virtual machine with the value in src1 as the exit code. Halts with an invalid
+operand instead if the value is larger than the largest 32 bit signed integer
+*/
+
+const NOP: u8 = 0x00;
+const MOV: u8 = 0x01;
+const ADD: u8 = 0x02;
+const SUB: u8 = 0x03;
+const MUL: u8 = 0x04;
+const DIVT: u8 = 0x05;
+const DIVR: u8 = 0x06;
+const REM: u8 = 0x07;
+const CGT: u8 = 0x08;
+const CLT: u8 = 0x09;
+const JMP: u8 = 0x0A;
+const JIE: u8 = 0x0B;
+const JNE: u8 = 0x0C;
+const PUT_I: u8 = 0x0D;
+const PUT_C: u8 = 0x0E;
+const IMZ: u8 = 0x0F;
+const EQU: u8 = 0x10;
+const AND: u8 = 0x11;
+const OR: u8 = 0x12;
+const XOR: u8 = 0x13;
+const NOT: u8 = 0x14;
+const SHL: u8 = 0x15;
+const SHR: u8 = 0x16;
+const PUSH: u8 = 0x17;
+const POP: u8 = 0x18;
+const CALL: u8 = 0x19;
+const RET: u8 = 0x1A;
+const CGE: u8 = 0x1B;
+const CLE: u8 = 0x1C;
+const GET_I: u8 = 0x1D;
+const GET_C: u8 = 0x1E;
+const PUT_S: u8 = 0x1F;
+const MEMCPY: u8 = 0x20;
+const MEMSET: u8 = 0x21;
+const SWAP: u8 = 0x22;
+const BSWAP: u8 = 0x23;
+const POPCNT: u8 = 0x24;
+const CLZ: u8 = 0x25;
+const CTZ: u8 = 0x26;
+const ROTL: u8 = 0x27;
+const ROTR: u8 = 0x28;
+const INC: u8 = 0x29;
+const DEC: u8 = 0x2A;
+const NEG: u8 = 0x2B;
+const ABS: u8 = 0x2C;
+const ASSERT: u8 = 0x2D;
+const NASSERT: u8 = 0x2E;
+const TRAP: u8 = 0x2F;
+const BOUNDS: u8 = 0x30;
+const SCGT: u8 = 0x31;
+const SCLT: u8 = 0x32;
+const SEQU: u8 = 0x33;
+const ADDO: u8 = 0x34;
+const SUBO: u8 = 0x35;
+const MULO: u8 = 0x36;
+const SADD: u8 = 0x37;
+const SSUB: u8 = 0x38;
+const SMUL: u8 = 0x39;
+const MIN: u8 = 0x3A;
+const MAX: u8 = 0x3B;
+const MOD: u8 = 0x3C;
+const IMM: u8 = 0x3D;
+const RANDOM: u8 = 0x3E;
+const CLOCK: u8 = 0x3F;
+const PUT_H: u8 = 0x40;
+const STRLEN: u8 = 0x41;
+const STRCMP: u8 = 0x42;
+const MEMMOVE: u8 = 0x91;
+const PRTMM: u8 = 0x92;
+const SYSCALL: u8 = 0x93;
+const SAVEFP: u8 = 0x94;
+const RESTFP: u8 = 0x95;
+const SWITCH: u8 = 0x96;
+const COSAVE: u8 = 0x97;
+const CORESTORE: u8 = 0x98;
+const PROFREGION: u8 = 0x99;
+const REPEAT: u8 = 0x9A;
+const SETPRED: u8 = 0x9B;
+const CLRPRED: u8 = 0x9C;
+const ADDMOD: u8 = 0x9D;
+const MULMOD: u8 = 0x9E;
+const MODPOW: u8 = 0x9F;
+const INVMOD: u8 = 0xA0;
+const XORSHIFT64: u8 = 0xA1;
+const CHACHA20BLK: u8 = 0xA2;
+const SHA256COMP: u8 = 0xA3;
+const POPSTACK: u8 = 0xA4;
+const PEEK: u8 = 0xA5;
+const PUSHPC: u8 = 0xA6;
+const POPPC: u8 = 0xA7;
+const VCMPEQ8: u8 = 0xA8;
+const VCMPGT8: u8 = 0xA9;
+const DOTPROD: u8 = 0xAA;
+const TLOOKUP: u8 = 0xAB;
+const SETOUT: u8 = 0xAC;
+const SETIN: u8 = 0xAD;
+const BLOOPN: u8 = 0xAE;
+const LEND: u8 = 0xAF;
+const RFIND: u8 = 0xB0;
+const SPLITB: u8 = 0xB1;
+const JOINB: u8 = 0xB2;
+const INTERLEAVE: u8 = 0xB3;
+const DEINTERLEAVE: u8 = 0xB4;
+const RLEENC: u8 = 0xB5;
+const RLEDEC: u8 = 0xB6;
+const DELTAENC: u8 = 0xB7;
+const DELTADEC: u8 = 0xB8;
+const NIBPK: u8 = 0xB9;
+const NIBUPK: u8 = 0xBA;
+const FIBSTEP: u8 = 0xBB;
+const BSEARCH: u8 = 0xBC;
+const QSORT: u8 = 0xBD;
+const UNIQSORT: u8 = 0xBE;
+const PCTILE: u8 = 0xBF;
+const HISTO: u8 = 0xC0;
+const WSAMPLE: u8 = 0xC1;
+const BITMATMUL: u8 = 0xC2;
+const POPCNTA: u8 = 0xC3;
+const DITHER: u8 = 0xC4;
+const HLTE: u8 = 0xFE;
+const HLT: u8 = 0xFF;
+
+use crate::error::TransientError;
+use crate::image::{read_image, read_symbols};
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::collections::hash_map::DefaultHasher;
+#[cfg(feature = "std")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "std")]
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+#[cfg(feature = "std")]
+use std::time::{Instant, SystemTime};
+
+/// Transient memory size used by the virtual machine binary
+pub const TRANSIENT_MEM_MAX: usize = 0xFFFFFF;
+const SHA256_ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+pub const DELAY_SLOT_FLAG: u8 = 0x40;
+pub const PREDICATE_FLAG: u8 = 0x80;
+pub const ADDRESS_SIZE: u8 = 4; // Width of transient addresses in instructions and memory
+const SYSCALL_ARGS: [u32; 4] = [0x00, 0x04, 0x08, 0x0C];
+const SYSCALL_FAILED: u64 = 0xFFFFFFFF;
+
+/// Layout of the operands that follow the opcode of an instruction
+pub enum OperandLayout {
+    /// A size byte followed by source1, source2, and destination, of which the listed ones are used
+    Standard(&'static [usize]),
+    /// A size byte followed by the given number of addresses
+    Sized(usize),
+    /// The given number of addresses
+    Unsized(usize),
+    /// Two addresses, an element size byte, and another address (TLOOKUP)
+    TableLookup,
+    /// A size byte, a value of that many bytes, and an address (IMM)
+    Immediate,
+}
+
+impl OperandLayout {
+    /// Returns the length in bytes of an instruction with this layout, including the opcode, given
+    /// its size byte
+    pub fn length(&self, size: u8) -> usize {
+        let address_size = ADDRESS_SIZE as usize;
+        match self {
+            OperandLayout::Standard(_) | OperandLayout::TableLookup => 2 + 3 * address_size,
+            OperandLayout::Sized(count) => 2 + count * address_size,
+            OperandLayout::Unsized(count) => 1 + count * address_size,
+            OperandLayout::Immediate => 2 + size_decode(size) as usize + address_size,
+        }
+    }
+}
+
+/// Opcode, mnemonic, and operand layout of every instruction, sorted by opcode
+pub const OPERATIONS: &[(u8, &str, OperandLayout)] = &[
+    (NOP, "nop", OperandLayout::Unsized(0)),
+    (MOV, "mov", OperandLayout::Standard(&[0, 2])),
+    (ADD, "add", OperandLayout::Standard(&[0, 1, 2])),
+    (SUB, "sub", OperandLayout::Standard(&[0, 1, 2])),
+    (MUL, "mul", OperandLayout::Standard(&[0, 1, 2])),
+    (DIVT, "divt", OperandLayout::Standard(&[0, 1, 2])),
+    (DIVR, "divr", OperandLayout::Standard(&[0, 1, 2])),
+    (REM, "rem", OperandLayout::Standard(&[0, 1, 2])),
+    (CGT, "cgt", OperandLayout::Standard(&[0, 1, 2])),
+    (CLT, "clt", OperandLayout::Standard(&[0, 1, 2])),
+    (JMP, "jmp", OperandLayout::Standard(&[0])),
+    (JIE, "jie", OperandLayout::Standard(&[0, 1])),
+    (JNE, "jne", OperandLayout::Standard(&[0, 1])),
+    (PUT_I, "puti", OperandLayout::Standard(&[0])),
+    (PUT_C, "putc", OperandLayout::Standard(&[0])),
+    (IMZ, "imz", OperandLayout::Standard(&[2])),
+    (EQU, "equ", OperandLayout::Standard(&[0, 1, 2])),
+    (AND, "and", OperandLayout::Standard(&[0, 1, 2])),
+    (OR, "or", OperandLayout::Standard(&[0, 1, 2])),
+    (XOR, "xor", OperandLayout::Standard(&[0, 1, 2])),
+    (NOT, "not", OperandLayout::Standard(&[0, 2])),
+    (SHL, "shl", OperandLayout::Standard(&[0, 1, 2])),
+    (SHR, "shr", OperandLayout::Standard(&[0, 1, 2])),
+    (PUSH, "push", OperandLayout::Standard(&[0])),
+    (POP, "pop", OperandLayout::Standard(&[2])),
+    (CALL, "call", OperandLayout::Unsized(1)),
+    (RET, "ret", OperandLayout::Unsized(0)),
+    (CGE, "cge", OperandLayout::Standard(&[0, 1, 2])),
+    (CLE, "cle", OperandLayout::Standard(&[0, 1, 2])),
+    (GET_I, "geti", OperandLayout::Standard(&[2])),
+    (GET_C, "getc", OperandLayout::Standard(&[2])),
+    (PUT_S, "puts", OperandLayout::Standard(&[0])),
+    (MEMCPY, "memcpy", OperandLayout::Unsized(3)),
+    (MEMSET, "memset", OperandLayout::Unsized(3)),
+    (SWAP, "swap", OperandLayout::Standard(&[0, 1])),
+    (BSWAP, "bswap", OperandLayout::Standard(&[0, 2])),
+    (POPCNT, "popcnt", OperandLayout::Standard(&[0, 2])),
+    (CLZ, "clz", OperandLayout::Standard(&[0, 2])),
+    (CTZ, "ctz", OperandLayout::Standard(&[0, 2])),
+    (ROTL, "rotl", OperandLayout::Standard(&[0, 1, 2])),
+    (ROTR, "rotr", OperandLayout::Standard(&[0, 1, 2])),
+    (INC, "inc", OperandLayout::Standard(&[0])),
+    (DEC, "dec", OperandLayout::Standard(&[0])),
+    (NEG, "neg", OperandLayout::Standard(&[0, 2])),
+    (ABS, "abs", OperandLayout::Standard(&[0, 2])),
+    (ASSERT, "assert", OperandLayout::Standard(&[0])),
+    (NASSERT, "nassert", OperandLayout::Standard(&[0])),
+    (TRAP, "trap", OperandLayout::Unsized(0)),
+    (BOUNDS, "bounds", OperandLayout::Sized(4)),
+    (SCGT, "scgt", OperandLayout::Standard(&[0, 1, 2])),
+    (SCLT, "sclt", OperandLayout::Standard(&[0, 1, 2])),
+    (SEQU, "sequ", OperandLayout::Standard(&[0, 1, 2])),
+    (ADDO, "addo", OperandLayout::Sized(4)),
+    (SUBO, "subo", OperandLayout::Sized(4)),
+    (MULO, "mulo", OperandLayout::Sized(4)),
+    (SADD, "sadd", OperandLayout::Standard(&[0, 1, 2])),
+    (SSUB, "ssub", OperandLayout::Standard(&[0, 1, 2])),
+    (SMUL, "smul", OperandLayout::Standard(&[0, 1, 2])),
+    (MIN, "min", OperandLayout::Standard(&[0, 1, 2])),
+    (MAX, "max", OperandLayout::Standard(&[0, 1, 2])),
+    (MOD, "mod", OperandLayout::Standard(&[0, 1, 2])),
+    (IMM, "imm", OperandLayout::Immediate),
+    (RANDOM, "rand", OperandLayout::Standard(&[2])),
+    (CLOCK, "clock", OperandLayout::Standard(&[2])),
+    (PUT_H, "puth", OperandLayout::Standard(&[0])),
+    (STRLEN, "strlen", OperandLayout::Unsized(2)),
+    (STRCMP, "strcmp", OperandLayout::Unsized(3)),
+    (MEMMOVE, "memmove", OperandLayout::Unsized(3)),
+    (PRTMM, "prtmm", OperandLayout::Unsized(0)),
+    (SYSCALL, "syscall", OperandLayout::Unsized(1)),
+    (SAVEFP, "savefp", OperandLayout::Unsized(1)),
+    (RESTFP, "restfp", OperandLayout::Unsized(1)),
+    (SWITCH, "switch", OperandLayout::Unsized(4)),
+    (COSAVE, "cosave", OperandLayout::Unsized(1)),
+    (CORESTORE, "corestore", OperandLayout::Unsized(1)),
+    (PROFREGION, "profr", OperandLayout::Unsized(1)),
+    (REPEAT, "rep", OperandLayout::Unsized(1)),
+    (SETPRED, "setpred", OperandLayout::Unsized(1)),
+    (CLRPRED, "clrpred", OperandLayout::Unsized(0)),
+    (ADDMOD, "addmod", OperandLayout::Sized(4)),
+    (MULMOD, "mulmod", OperandLayout::Sized(4)),
+    (MODPOW, "modpow", OperandLayout::Sized(4)),
+    (INVMOD, "invmod", OperandLayout::Sized(4)),
+    (XORSHIFT64, "xorsh64", OperandLayout::Unsized(2)),
+    (CHACHA20BLK, "chacha20", OperandLayout::Unsized(2)),
+    (SHA256COMP, "sha256", OperandLayout::Unsized(2)),
+    (POPSTACK, "popstk", OperandLayout::Unsized(1)),
+    (PEEK, "peek", OperandLayout::Sized(1)),
+    (PUSHPC, "pushpc", OperandLayout::Unsized(0)),
+    (POPPC, "poppc", OperandLayout::Unsized(0)),
+    (VCMPEQ8, "vcmpeq8", OperandLayout::Unsized(4)),
+    (VCMPGT8, "vcmpgt8", OperandLayout::Unsized(4)),
+    (DOTPROD, "dotprod", OperandLayout::Unsized(4)),
+    (TLOOKUP, "tlookup", OperandLayout::TableLookup),
+    (SETOUT, "setout", OperandLayout::Unsized(1)),
+    (SETIN, "setin", OperandLayout::Unsized(1)),
+    (BLOOPN, "bloopn", OperandLayout::Unsized(2)),
+    (LEND, "lend", OperandLayout::Unsized(0)),
+    (RFIND, "rfind", OperandLayout::Unsized(4)),
+    (SPLITB, "splitb", OperandLayout::Sized(2)),
+    (JOINB, "joinb", OperandLayout::Sized(2)),
+    (INTERLEAVE, "interleave", OperandLayout::Unsized(4)),
+    (DEINTERLEAVE, "deinterleave", OperandLayout::Unsized(4)),
+    (RLEENC, "rleenc", OperandLayout::Unsized(5)),
+    (RLEDEC, "rledec", OperandLayout::Unsized(5)),
+    (DELTAENC, "deltaenc", OperandLayout::Unsized(3)),
+    (DELTADEC, "deltadec", OperandLayout::Unsized(3)),
+    (NIBPK, "nibpk", OperandLayout::Unsized(3)),
+    (NIBUPK, "nibupk", OperandLayout::Unsized(3)),
+    (FIBSTEP, "fibstep", OperandLayout::Unsized(2)),
+    (BSEARCH, "bsearch", OperandLayout::Unsized(4)),
+    (QSORT, "qsort", OperandLayout::Unsized(4)),
+    (UNIQSORT, "uniqsort", OperandLayout::Unsized(3)),
+    (PCTILE, "pctile", OperandLayout::Unsized(4)),
+    (HISTO, "histo", OperandLayout::Unsized(3)),
+    (WSAMPLE, "wsample", OperandLayout::Unsized(4)),
+    (BITMATMUL, "bitmatmul", OperandLayout::Unsized(6)),
+    (POPCNTA, "popcnta", OperandLayout::Unsized(3)),
+    (DITHER, "dither", OperandLayout::Unsized(4)),
+    (HLTE, "hlte", OperandLayout::Standard(&[0])),
+    (HLT, "hlt", OperandLayout::Standard(&[])),
+];
+
+/// Returns the opcode, mnemonic, and operand layout of an opcode, or None if it isn't a valid
+/// instruction
+pub fn find_operation(opcode: u8) -> Option<&'static (u8, &'static str, OperandLayout)> {
+    let index = OPERATIONS.binary_search_by_key(&opcode, |(x, ..)| *x).ok()?;
+    Some(&OPERATIONS[index])
+}
+
+/// Called with the raw bytes of an instruction and a program counter
+pub type InstructionHook = Box<dyn FnMut(&[u8], usize)>;
+
+/// Byte-wise output and input of the host, for processors that run without stdout and stdin
+pub trait TransientIO {
+    /// Writes a byte of program output
+    fn write_byte(&mut self, b: u8);
+    /// Reads a byte of program input, or None at the end of the input
+    fn read_byte(&mut self) -> Option<u8>;
+}
+
+/// Whether the processor is executing, halted, or paused at a breakpoint
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransientMode {
+    RUNNING,
+    HALTED(Option<i32>), // The exit code, if the program halted with HLTE
+    PAUSED,
+}
+
+/// A copy of the processor state taken by `TransientState::snapshot`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransientSnapshot {
+    pub memory: Vec<u8>,
+    pub image_length: usize,
+    pub program_counter: usize,
+    pub frame_pointer: usize,
+    pub stack_pointer: usize,
+    pub condition_register: u8,
+    pub mode: TransientMode,
+    pub loops: Vec<(usize, u64, usize)>,
+}
+
+/// A transient processor with TRANSIENT_MEM_MAX bytes of transient memory
+pub struct TransientState<const TRANSIENT_MEM_MAX: usize> {
+    pub memory: [u8; TRANSIENT_MEM_MAX],
+    pub image_length: usize, // Length of executable code in memory
+    pub program_counter: usize,
+    pub frame_pointer: usize,
+    pub stack_pointer: usize,
+    pub condition_register: u8,
+    pub debug_assertions: bool, // Whether ASSERT and NASSERT are checked
+    pub signed_arithmetic: bool, // Whether SADD, SSUB, SMUL, MIN, and MAX treat their operands as signed
+    pub mode: TransientMode,
+    pub sandbox: Option<(Range<usize>, Range<usize>)>, // Allowed code and data regions
+    pub symbols: Vec<(String, usize, usize)>, // Name, address, size
+    #[cfg(feature = "std")]
+    pub files: Vec<Option<File>>, // Files opened by SYSCALL, file descriptor is index + 3
+    pub profile: Option<BTreeMap<u32, u64>>, // Instructions executed per region, if profiling
+    pub profile_region: u32,
+    #[cfg(feature = "std")]
+    pub output_devices: BTreeMap<u32, Box<dyn Write>>, // Registered output devices by id
+    #[cfg(feature = "std")]
+    pub input_devices: BTreeMap<u32, Box<dyn Read>>, // Registered input devices by id
+    pub output_device: u32, // Active output device, 0 is stdout unless registered
+    pub input_device: u32, // Active input device, 0 is stdin unless registered
+    pub io: Option<Box<dyn TransientIO>>, // Host I/O used for device 0 in place of stdout and stdin
+    pub loops: Vec<(usize, u64, usize)>, // Active BLOOPN loops: body start, iterations left, return address
+    pub breakpoints: BTreeSet<usize>, // Addresses that pause run() before they execute
+    pub pre_hook: Option<InstructionHook>, // Called with every instruction and its address
+    pub post_hook: Option<InstructionHook>, // Called with every instruction and the address of the next
+    pub rng_state: u64, // State of the xorshift64 generator behind RANDOM
+    #[cfg(feature = "std")]
+    pub start_time: Instant, // Creation time of the processor, read by CLOCK
+    pub instructions_executed: u64, // Instructions executed since the processor was created or reset
+    pub touched: Option<Vec<bool>>, // Bytes written by the program, if write tracking is enabled
+}
+
+impl<const TRANSIENT_MEM_MAX: usize> Default for TransientState<TRANSIENT_MEM_MAX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const TRANSIENT_MEM_MAX: usize> TransientState<TRANSIENT_MEM_MAX> {
+    /// Initialize a new, empty instance of a transient processor/state with a transient memory
+    /// size of TRANSIENT_MEM_MAX bytes.
+    pub fn new() -> Self {
+        TransientState {
+            memory: [0x00; TRANSIENT_MEM_MAX],
+            image_length: 0,
+            program_counter: 0,
+            frame_pointer: 0,
+            stack_pointer: TRANSIENT_MEM_MAX,
+            condition_register: 1,
+            debug_assertions: true,
+            signed_arithmetic: false,
+            mode: TransientMode::HALTED(None),
+            sandbox: None,
+            symbols: vec![],
+            #[cfg(feature = "std")]
+            files: vec![],
+            profile: None,
+            profile_region: 0,
+            #[cfg(feature = "std")]
+            output_devices: BTreeMap::new(),
+            #[cfg(feature = "std")]
+            input_devices: BTreeMap::new(),
+            output_device: 0,
+            input_device: 0,
+            io: None,
+            loops: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            pre_hook: None,
+            post_hook: None,
+            rng_state: time_seed(),
+            #[cfg(feature = "std")]
+            start_time: Instant::now(),
+            instructions_executed: 0,
+            touched: None,
+        }
+    }
+    /// Initialize a new processor like `new` that writes its output to and reads its input from
+    /// `io` instead of stdout and stdin.
+    pub fn new_with_io<I: TransientIO + 'static>(io: I) -> Self {
+        TransientState { io: Some(Box::new(io)), ..Self::new() }
+    }
+    /// Restricts the processor to the given code and data regions. Any memory access outside of
+    /// both regions, or a program counter outside of the code region, halts the processor.
+    pub fn enable_sandbox(&mut self, code_base: usize, code_len: usize, data_base: usize, data_len: usize) {
+        self.sandbox = Some((code_base..code_base + code_len, data_base..data_base + data_len));
+    }
+    /// Enables counting the instructions executed in each region marked by PROFREGION. Code before
+    /// the first PROFREGION is counted towards region 0.
+    pub fn enable_profiling(&mut self) {
+        self.profile = Some(BTreeMap::new());
+    }
+    /// Enables recording which bytes of transient memory the program writes to
+    pub fn enable_write_tracking(&mut self) {
+        self.touched = Some(vec![false; TRANSIENT_MEM_MAX]);
+    }
+    /// Returns the number of bytes the program wrote to at least once, if write tracking is enabled
+    pub fn touched_bytes(&self) -> Option<usize> {
+        Some(self.touched.as_ref()?.iter().filter(|x| **x).count())
+    }
+    fn mark_touched(&mut self, address: usize, length: usize) {
+        if let Some(touched) = self.touched.as_mut().and_then(|x| x.get_mut(address..address + length)) {
+            touched.fill(true);
+        }
+    }
+    /// Formats the instructions executed per region, if profiling is enabled.
+    pub fn profile_report(&self) -> Option<String> {
+        let profile = self.profile.as_ref()?;
+        let total: u64 = profile.values().sum();
+        let mut out = String::new();
+        for (region, count) in profile {
+            out += &format!("Region {}: {} instructions ({:.1}%)\n", region, count, *count as f64 * 100.0 / total as f64);
+        }
+        Some(out)
+    }
+    /// Registers an output device that programs can redirect their output to with SETOUT.
+    #[cfg(feature = "std")]
+    pub fn register_output_device(&mut self, id: u32, device: Box<dyn Write>) {
+        self.output_devices.insert(id, device);
+    }
+    /// Registers an input device that programs can redirect their input from with SETIN.
+    #[cfg(feature = "std")]
+    pub fn register_input_device(&mut self, id: u32, device: Box<dyn Read>) {
+        self.input_devices.insert(id, device);
+    }
+    /// Writes to the active output device. Device 0 is the `TransientIO` of the processor if it
+    /// has one, and stdout otherwise.
+    pub fn write_output(&mut self, data: &[u8]) -> Result<usize, TransientError> {
+        #[cfg(feature = "std")]
+        if let Some(device) = self.output_devices.get_mut(&self.output_device) {
+            return Ok(device.write(data)?);
+        }
+        match &mut self.io {
+            Some(io) => {
+                data.iter().for_each(|b| io.write_byte(*b));
+                Ok(data.len())
+            }
+            #[cfg(feature = "std")]
+            None => Ok(std::io::stdout().write(data)?),
+            #[cfg(not(feature = "std"))]
+            None => Ok(data.len()),
+        }
+    }
+    /// Reads from the active input device. Device 0 is the `TransientIO` of the processor if it
+    /// has one, and stdin otherwise.
+    pub fn read_input(&mut self, data: &mut [u8]) -> Result<usize, TransientError> {
+        #[cfg(feature = "std")]
+        if let Some(device) = self.input_devices.get_mut(&self.input_device) {
+            return Ok(device.read(data)?);
+        }
+        match &mut self.io {
+            Some(io) => {
+                let mut count = 0;
+                while let Some(b) = data.get(count).and_then(|_| io.read_byte()) {
+                    data[count] = b;
+                    count += 1;
+                }
+                Ok(count)
+            }
+            #[cfg(feature = "std")]
+            None => Ok(std::io::stdin().read(data)?),
+            #[cfg(not(feature = "std"))]
+            None => Ok(0),
+        }
+    }
+    /// Reads a single byte from the active input device, or None at the end of the input
+    pub fn read_input_byte(&mut self) -> Result<Option<u8>, TransientError> {
+        let mut byte = [0u8];
+        Ok(match self.read_input(&mut byte)? {
+            0 => None,
+            _ => Some(byte[0]),
+        })
+    }
+    /// Reads a whitespace-delimited decimal integer from the active input device
+    pub fn read_integer(&mut self) -> Result<u64, TransientError> {
+        let mut digits = String::new();
+        while let Some(byte) = self.read_input_byte()? {
+            if !byte.is_ascii_whitespace() {
+                digits.push(byte as char);
+            } else if !digits.is_empty() {
+                break;
+            }
+        }
+        if digits.is_empty() {
+            return Err(TransientError::EndOfInput);
+        }
+        digits.parse().map_err(|_| TransientError::InvalidInput("Input is not an integer"))
+    }
+    /// Verifies that `length` bytes starting at `address` lie within transient memory and within
+    /// the sandbox, if one is enabled.
+    pub fn sandbox_check(&self, address: usize, length: usize) -> Result<(), TransientError> {
+        let end = match address.checked_add(length) {
+            Some(end) if end <= self.memory.len() => end,
+            _ => return Err(TransientError::OutOfBoundsAccess { addr: address, limit: self.memory.len() }),
+        };
+        if let Some((code, data)) = &self.sandbox {
+            let contained = |region: &Range<usize>| region.start <= address && end <= region.end;
+            if !contained(code) && !contained(data) {
+                return Err(TransientError::SandboxViolation { addr: address });
+            }
+        }
+        Ok(())
+    }
+    /// Returns the length in bytes of `count` elements of `element_size` bytes at `address`, failing
+    /// with an out of bounds access if the length doesn't fit in a usize
+    fn region_length(&self, address: usize, count: usize, element_size: usize) -> Result<usize, TransientError> {
+        count
+            .checked_mul(element_size)
+            .ok_or(TransientError::OutOfBoundsAccess { addr: address, limit: self.memory.len() })
+    }
+    /// Adds `offset` to a transient address, failing with an out of bounds access if the result
+    /// doesn't fit in an address
+    fn address_offset(&self, address: u32, offset: u64) -> Result<u32, TransientError> {
+        (address as u64)
+            .checked_add(offset)
+            .and_then(|x| u32::try_from(x).ok())
+            .ok_or(TransientError::OutOfBoundsAccess { addr: address as usize, limit: self.memory.len() })
+    }
+    /// Advances every address operand of a sized instruction by its operand size, so that a
+    /// repeated instruction walks over arrays. Unsized instructions are left as they are
+    fn advance_operands(&self, instruction: &mut [u8]) -> Result<(), TransientError> {
+        let opcode = instruction[0];
+        if !has_size_byte(opcode) {
+            return Ok(());
+        }
+        let size = size_decode(instruction[1]) as usize;
+        // The value of an IMM is stored inline in front of its destination
+        let operands = if opcode == IMM { 2 + size } else { 2 };
+        for operand in instruction[operands..].chunks_exact_mut(ADDRESS_SIZE as usize) {
+            let address = self.address_offset(address_decode(operand), size as u64)?;
+            operand.copy_from_slice(&address.to_be_bytes());
+        }
+        Ok(())
+    }
+    /// Returns the bytes of the null-terminated string at `address`, excluding the terminator
+    pub fn string_at(&self, address: usize) -> Result<&[u8], TransientError> {
+        let mut end = address;
+        loop {
+            self.sandbox_check(end, 1)?;
+            if self.memory[end] == 0x00 {
+                return Ok(&self.memory[address..end]);
+            }
+            end += 1;
+        }
+    }
+    /// Loads a transient memory image into a state/processor at a specified offset. The header of
+    /// the image is validated and not loaded. The variables of the image's `SYMB` section become the
+    /// symbols printed by PRTMM.
+    pub fn load_image(&mut self, offset: usize, image: &[u8]) -> Result<(), TransientError> {
+        let symbols = read_symbols(image)?;
+        let (header, body) = read_image(image)?;
+        let image = &body[..header.image_size];
+        let size = offset.saturating_add(image.len());
+        if size > TRANSIENT_MEM_MAX {
+            return Err(TransientError::ImageTooLarge { size, limit: TRANSIENT_MEM_MAX });
+        }
+        // Copy over image data
+        self.memory[offset..image.len() + offset].copy_from_slice(image);
+        // Set image lengt of processor data
+        self.image_length = image.len();
+        self.symbols = symbols;
+        Ok(())
+    }
+    /// Formats every loaded symbol with its address, size, and current value, one per line. Values
+    /// that hold null-terminated text are printed as strings, other values of up to 8 bytes as
+    /// integers, and longer ones as their bytes in hexadecimal.
+    pub fn format_memory_map(&self) -> String {
+        let mut report = String::new();
+        for (name, address, size) in &self.symbols {
+            let value = match self.memory.get(*address..).and_then(|x| x.get(..*size)) {
+                Some([text @ .., 0x00]) if !text.is_empty() && text.iter().all(|x| x.is_ascii_graphic() || b" \n\t".contains(x)) => {
+                    format!("{:?}", String::from_utf8_lossy(text))
+                }
+                Some(bytes) if bytes.len() <= 8 => u64::from_be_bytes(u64_pad_be(bytes)).to_string(),
+                Some(bytes) => bytes.iter().map(|x| format!("{:02X}", x)).collect::<Vec<String>>().join(" "),
+                None => "outside of transient memory".to_owned(),
+            };
+            report += &format!("{}: {:#06X} ({} bytes) = {}\n", name, address, size, value);
+        }
+        report
+    }
+    /// Copies the memory and registers of the processor. Devices, files, and hooks aren't included.
+    pub fn snapshot(&self) -> TransientSnapshot {
+        TransientSnapshot {
+            memory: self.memory.to_vec(),
+            image_length: self.image_length,
+            program_counter: self.program_counter,
+            frame_pointer: self.frame_pointer,
+            stack_pointer: self.stack_pointer,
+            condition_register: self.condition_register,
+            mode: self.mode,
+            loops: self.loops.clone(),
+        }
+    }
+    /// Overwrites the memory and registers of the processor with a snapshot. Panics if the snapshot
+    /// was taken of a processor with a different memory size.
+    pub fn restore(&mut self, snap: &TransientSnapshot) {
+        self.memory.copy_from_slice(&snap.memory);
+        self.image_length = snap.image_length;
+        self.program_counter = snap.program_counter;
+        self.frame_pointer = snap.frame_pointer;
+        self.stack_pointer = snap.stack_pointer;
+        self.condition_register = snap.condition_register;
+        self.mode = snap.mode;
+        self.loops.clone_from(&snap.loops);
+    }
+    /// Returns the processor to the state it was created in, with zeroed memory. Devices, files,
+    /// symbols, breakpoints, and hooks are kept.
+    pub fn reset(&mut self) {
+        self.memory.fill(0x00);
+        self.image_length = 0;
+        self.reset_registers();
+    }
+    /// Resets the registers and zeroes the code section up to `code_end`, keeping the data
+    /// variables after it.
+    pub fn reset_keep_data(&mut self, code_end: usize) {
+        let code_end = code_end.min(self.memory.len());
+        self.memory[..code_end].fill(0x00);
+        self.reset_registers();
+    }
+    fn reset_registers(&mut self) {
+        self.program_counter = 0;
+        self.frame_pointer = 0;
+        self.stack_pointer = TRANSIENT_MEM_MAX;
+        self.condition_register = 1;
+        self.mode = TransientMode::HALTED(None);
+        self.profile_region = 0;
+        self.loops.clear();
+        self.instructions_executed = 0;
+    }
+    /// Pauses run() before the instruction at `addr` is executed
+    pub fn set_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.insert(addr);
+    }
+    /// Removes the breakpoint at `addr`, if there is one
+    pub fn clear_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.remove(&addr);
+    }
+    /// Returns whether the processor stopped at a breakpoint, as opposed to halting
+    pub fn is_paused(&self) -> bool {
+        self.mode == TransientMode::PAUSED
+    }
+    /// Seeds the generator behind RANDOM, so that a program produces the same numbers on every
+    /// run. A seed of 0 is replaced with 1, as xorshift64 never leaves the zero state.
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng_state = seed.max(1);
+    }
+    /// Returns the exit code the program halted with, if it halted with HLTE
+    pub fn exit_code(&self) -> Option<i32> {
+        match self.mode {
+            TransientMode::HALTED(code) => code,
+            _ => None,
+        }
+    }
+    /// Calls `f` with the bytes and address of every instruction right before it is executed
+    pub fn set_hook(&mut self, f: impl FnMut(&[u8], usize) + 'static) {
+        self.pre_hook = Some(Box::new(f));
+    }
+    /// Calls `f` with the bytes of every instruction and the new program counter right after it is
+    /// executed
+    pub fn set_post_hook(&mut self, f: impl FnMut(&[u8], usize) + 'static) {
+        self.post_hook = Some(Box::new(f));
+    }
+    /// Removes both instruction hooks
+    pub fn clear_hooks(&mut self) {
+        self.pre_hook = None;
+        self.post_hook = None;
+    }
+    /// Starts a loop that runs the processor until halted or a breakpoint is reached. A paused
+    /// processor ignores `start` and resumes from the breakpoint it stopped at.
+    pub fn run(&mut self, start: usize) -> Result<(), TransientError> {
+        // Don't pause on the breakpoint we're resuming from
+        let mut resuming = self.is_paused();
+        if !resuming {
+            self.program_counter = start;
+        }
+        self.mode = TransientMode::RUNNING;
+        while self.mode == TransientMode::RUNNING {
+            if !resuming && self.breakpoints.contains(&self.program_counter) {
+                self.mode = TransientMode::PAUSED;
+                break;
+            }
+            resuming = false;
+            self.step()?;
+        }
+        Ok(())
+    }
+    /// Executes the instruction at the program counter, moves the program counter to the next
+    /// instruction, and returns the number of bytes consumed. A jump with a delay slot consumes
+    /// the delay slot as well, which is checked, hooked, and counted like an instruction of its own.
+    pub fn step(&mut self) -> Result<usize, TransientError> {
+        if matches!(self.mode, TransientMode::HALTED(_)) {
+            return Err(TransientError::AlreadyHalted);
+        }
+        let instruction = self.fetch_instruction()?;
+        let mut consumed = instruction.len();
+        let mut next = self.execute_counted(&instruction)?;
+        if matches!(instruction[0], JMP | JIE | JNE) && instruction[1] & DELAY_SLOT_FLAG != 0 {
+            // Execute the delay slot before the jump takes effect
+            let fallthrough = self.program_counter + instruction.len();
+            self.call_post_hook(&instruction, fallthrough);
+            self.program_counter = fallthrough;
+            let slot = self.fetch_instruction()?;
+            if changes_control_flow(slot[0]) {
+                return Err(TransientError::InvalidOperand("A delay slot can't hold a jump or halt"));
+            }
+            let after_slot = self.execute_counted(&slot)?;
+            consumed += slot.len();
+            if next == fallthrough {
+                next = after_slot;
+            }
+            self.call_post_hook(&slot, next);
+        } else {
+            self.call_post_hook(&instruction, next);
+        }
+        self.program_counter = next;
+        Ok(consumed)
+    }
+    /// Resolves the instruction at the program counter, which must be inside the code region of
+    /// the sandbox
+    fn fetch_instruction(&self) -> Result<Vec<u8>, TransientError> {
+        if let Some((code, _)) = &self.sandbox {
+            if !code.contains(&self.program_counter) {
+                return Err(TransientError::SandboxViolation { addr: self.program_counter });
+            }
+        }
+        self.resolve_instruction(self.program_counter)
+    }
+    /// Executes an instruction at the program counter after calling the pre hook, counts it, and
+    /// returns the next program counter
+    fn execute_counted(&mut self, instruction: &[u8]) -> Result<usize, TransientError> {
+        if let Some(hook) = &mut self.pre_hook {
+            hook(instruction, self.program_counter);
+        }
+        let next = self.execute_instruction(instruction)?;
+        if let Some(profile) = &mut self.profile {
+            *profile.entry(self.profile_region).or_insert(0) += 1;
+        }
+        self.instructions_executed += 1;
+        Ok(next)
+    }
+    fn call_post_hook(&mut self, instruction: &[u8], next: usize) {
+        if let Some(hook) = &mut self.post_hook {
+            hook(instruction, next);
+        }
+    }
+    pub fn resolve_instruction(&self, base_ptr: usize) -> Result<Vec<u8>, TransientError> {
+        self.sandbox_check(base_ptr, 1)?;
+        // Fetch correct number of bytes depending on instruction
+        let opcode = self.memory[base_ptr];
+        let (_, _, layout) = find_operation(opcode).ok_or(TransientError::InvalidOpcode(opcode))?;
+        let size = match layout {
+            OperandLayout::Immediate => {
+                self.sandbox_check(base_ptr, 2)?;
+                self.memory[base_ptr + 1]
+            }
+            _ => 0,
+        };
+        let length = layout.length(size);
+        self.sandbox_check(base_ptr, length)?;
+        Ok(self.memory[base_ptr..][..length].to_vec())
+    }
+    /// Pushes a value of `size` bytes onto the stack
+    pub fn stack_push(&mut self, size: u8, value: u64) -> Result<(), TransientError> {
+        if self.stack_pointer < self.image_length + size as usize {
+            return Err(TransientError::StackOverflow);
+        }
+        self.stack_pointer -= size as usize;
+        self.memory_write(0, size, self.stack_pointer as u32, value)
+    }
+    /// Pops a value of `size` bytes from the stack
+    pub fn stack_pop(&mut self, size: u8) -> Result<u64, TransientError> {
+        if size as usize > TRANSIENT_MEM_MAX.saturating_sub(self.stack_pointer) {
+            return Err(TransientError::StackUnderflow);
+        }
+        let value = self.memory_fetch(0, size, self.stack_pointer as u32)?;
+        self.stack_pointer += size as usize;
+        Ok(value)
+    }
+    pub fn memory_fetch(&self, pointer_mode: u8, address_size: u8, input: u32) -> Result<u64, TransientError> {
+        if address_size > 8 {
+            return Err(TransientError::InvalidOperand("Operand size must be at most 8 bytes"));
+        }
+        match pointer_mode {
+            0 => {
+                self.sandbox_check(input as usize, address_size as usize)?;
+                Ok(u64::from_be_bytes(u64_pad_be(&self.memory[input as usize..][..address_size as usize])))
+            }
+            1 => {
+                self.sandbox_check(input as usize, 4)?;
+                let pointer_bytes = &self.memory[input as usize..][..4];
+                let pointer = u32::from_be_bytes(pointer_bytes.try_into().expect("[Halt]: Memory fetch failed: Couldn't parse internal memory slice"));
+                self.sandbox_check(pointer as usize, address_size as usize)?;
+                Ok(u64::from_be_bytes(u64_pad_be(&self.memory[pointer as usize..][..address_size as usize])))
+            }
+            2 => {
+                Ok(input as u64)
+            }
+            _ => {
+                Err(TransientError::InvalidPointerMode(pointer_mode))
+            }
+        }
+    }
+    pub fn memory_write(&mut self, pointer_mode: u8, address_size: u8, input: u32, data: u64) -> Result<(), TransientError> {
+        if address_size > 8 {
+            return Err(TransientError::InvalidOperand("Operand size must be at most 8 bytes"));
+        }
+        let bytes = &data.to_be_bytes()[8 - address_size as usize..];
+        match pointer_mode {
+            0 => {
+                self.sandbox_check(input as usize, address_size as usize)?;
+                self.memory[input as usize..][..address_size as usize].copy_from_slice(bytes);
+                self.mark_touched(input as usize, address_size as usize);
+            }
+            1 => {
+                self.sandbox_check(input as usize, 4)?;
+                let pointer_bytes = &self.memory[input as usize..][..4];
+                let pointer = u32::from_be_bytes(pointer_bytes.try_into().expect("[Halt]: Memory write failed: Couldn't parse internal memory slice"));
+                self.sandbox_check(pointer as usize, address_size as usize)?;
+                self.memory[pointer as usize..][..address_size as usize].copy_from_slice(bytes);
+                self.mark_touched(pointer as usize, address_size as usize);
+            }
+            _ => {
+                return Err(TransientError::InvalidPointerMode(pointer_mode));
+            }
+        }
+        Ok(())
+    }
+    /// Dispatches a system call to the host and returns its result
+    pub fn syscall(&mut self, id: u32) -> Result<u64, TransientError> {
+        let mut args = [0usize; 4];
+        for (arg, address) in args.iter_mut().zip(SYSCALL_ARGS) {
+            *arg = self.memory_fetch(0, ADDRESS_SIZE, address)? as usize;
+        }
+        Ok(match id {
+            0 => {
+                self.mode = TransientMode::HALTED(Some(args[0] as i32));
+                0
+            }
+            1 => {
+                let (fd, buf, len) = (args[0], args[1], args[2]);
+                self.sandbox_check(buf, len)?;
+                let data = self.memory[buf..][..len].to_vec();
+                let written = match fd {
+                    1 => self.write_output(&data),
+                    #[cfg(feature = "std")]
+                    2 => std::io::stderr().write(&data).map_err(TransientError::from),
+                    #[cfg(feature = "std")]
+                    _ => match self.files.get_mut(fd.wrapping_sub(3)) {
+                        Some(Some(file)) => file.write(&data).map_err(TransientError::from),
+                        _ => return Ok(SYSCALL_FAILED),
+                    },
+                    #[cfg(not(feature = "std"))]
+                    _ => return Ok(SYSCALL_FAILED),
+                };
+                written.map_or(SYSCALL_FAILED, |x| x as u64)
+            }
+            2 => {
+                let (fd, buf, len) = (args[0], args[1], args[2]);
+                self.sandbox_check(buf, len)?;
+                let mut data = vec![0u8; len];
+                let read = match fd {
+                    0 => self.read_input(&mut data),
+                    #[cfg(feature = "std")]
+                    _ => match self.files.get_mut(fd.wrapping_sub(3)) {
+                        Some(Some(file)) => file.read(&mut data).map_err(TransientError::from),
+                        _ => return Ok(SYSCALL_FAILED),
+                    },
+                    #[cfg(not(feature = "std"))]
+                    _ => return Ok(SYSCALL_FAILED),
+                };
+                self.memory[buf..][..len].copy_from_slice(&data);
+                self.mark_touched(buf, len);
+                read.map_or(SYSCALL_FAILED, |x| x as u64)
+            }
+            #[cfg(feature = "std")]
+            3 => {
+                let (path, flags) = (args[0], args[1]);
+                let path = String::from_utf8_lossy(self.string_at(path)?).into_owned();
+                let file = match flags {
+                    0 => File::open(path),
+                    1 => File::create(path),
+                    2 => OpenOptions::new().append(true).create(true).open(path),
+                    _ => return Ok(SYSCALL_FAILED),
+                };
+                match file {
+                    Ok(file) => {
+                        self.files.push(Some(file));
+                        (self.files.len() + 2) as u64
+                    }
+                    Err(_) => SYSCALL_FAILED,
+                }
+            }
+            #[cfg(feature = "std")]
+            4 => match self.files.get_mut(args[0].wrapping_sub(3)) {
+                Some(file @ Some(_)) => {
+                    *file = None;
+                    0
+                }
+                _ => SYSCALL_FAILED,
+            },
+            #[cfg(not(feature = "std"))]
+            3 | 4 => SYSCALL_FAILED,
+            _ => return Err(TransientError::InvalidSyscall(id)),
+        })
+    }
+    /// Executes an instruction and returns the next program counter
+    pub fn execute_instruction(&mut self, instruction: &[u8]) -> Result<usize, TransientError> {
+        // Decodes instruction
+        let opcode = instruction[0];
+        if has_size_byte(opcode) && instruction[1] & PREDICATE_FLAG != 0 && self.condition_register == 0 {
+            return Ok(self.program_counter + instruction.len());
+        }
+        let next = self.program_counter + instruction.len();
+        Ok(match opcode {
+            NOP => next,
+            MOV => {
+                let size = size_decode(instruction[1]);
+                let value = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                self.memory_write(0, size, address_decode(&instruction[10..14]), value)?;
+                next
+            }
+            ADD | SUB | MUL | DIVT | DIVR | REM | EQU | CGT | CLT | AND | OR | XOR | SHL | SHR | CGE | CLE => {
+                let size = size_decode(instruction[1]);
+                let a = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let b = self.memory_fetch(0, size, address_decode(&instruction[6..10]))?;
+                if matches!(opcode, DIVT | DIVR | REM) && b == 0 {
+                    return Err(TransientError::DivisionByZero);
+                }
+                let result = match opcode {
+                    ADD => a.wrapping_add(b),
+                    SUB => a.wrapping_sub(b),
+                    MUL => a.wrapping_mul(b),
+                    DIVT => a / b,
+                    DIVR => ((a as u128 + b as u128 / 2) / b as u128) as u64,
+                    REM => a % b,
+                    EQU => (a == b) as u64,
+                    AND => a & b,
+                    OR => a | b,
+                    XOR => a ^ b,
+                    SHL => a.wrapping_shl(b as u32),
+                    SHR => a.wrapping_shr(b as u32),
+                    CGT => (a > b) as u64,
+                    CGE => (a >= b) as u64,
+                    CLE => (a <= b) as u64,
+                    _ => (a < b) as u64,
+                };
+                self.memory_write(0, size, address_decode(&instruction[10..14]), result)?;
+                next
+            }
+            JMP => address_decode(&instruction[2..6]) as usize,
+            JIE | JNE => {
+                let size = size_decode(instruction[1]);
+                let condition = self.memory_fetch(0, size, address_decode(&instruction[6..10]))? != 0;
+                if condition == (opcode == JIE) {
+                    address_decode(&instruction[2..6]) as usize
+                } else {
+                    next
+                }
+            }
+            PUT_I | PUT_C | PUT_H => {
+                let size = size_decode(instruction[1]);
+                let value = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let output = match opcode {
+                    PUT_I => value.to_string().into_bytes(),
+                    PUT_H => format!("0x{:0width$X}", value, width = size as usize * 2).into_bytes(),
+                    _ => vec![value as u8],
+                };
+                self.write_output(&output)?;
+                next
+            }
+            IMZ => {
+                let size = size_decode(instruction[1]);
+                self.memory_write(0, size, address_decode(&instruction[10..14]), self.image_length as u64)?;
+                next
+            }
+            NOT => {
+                let size = size_decode(instruction[1]);
+                let value = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                self.memory_write(0, size, address_decode(&instruction[10..14]), !value)?;
+                next
+            }
+            PUSH => {
+                let size = size_decode(instruction[1]);
+                let value = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                self.stack_push(size, value)?;
+                next
+            }
+            POP => {
+                let size = size_decode(instruction[1]);
+                let value = self.stack_pop(size)?;
+                self.memory_write(0, size, address_decode(&instruction[10..14]), value)?;
+                next
+            }
+            CALL => {
+                self.stack_push(ADDRESS_SIZE, next as u64)?;
+                address_decode(&instruction[1..5]) as usize
+            }
+            RET => self.stack_pop(ADDRESS_SIZE)? as usize,
+            GET_I => {
+                let size = size_decode(instruction[1]);
+                let value = self.read_integer()?;
+                self.memory_write(0, size, address_decode(&instruction[10..14]), value)?;
+                next
+            }
+            GET_C => {
+                let size = size_decode(instruction[1]);
+                let value = match self.read_input_byte()? {
+                    Some(byte) => byte as u64,
+                    None => return Err(TransientError::EndOfInput),
+                };
+                self.memory_write(0, size, address_decode(&instruction[10..14]), value)?;
+                next
+            }
+            PUT_S => {
+                let output = self.string_at(address_decode(&instruction[2..6]) as usize)?.to_vec();
+                self.write_output(&output)?;
+                next
+            }
+            MEMCPY => {
+                let dest = self.memory_fetch(0, ADDRESS_SIZE, address_decode(&instruction[1..5]))? as usize;
+                let src = self.memory_fetch(0, ADDRESS_SIZE, address_decode(&instruction[5..9]))? as usize;
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[9..13]))? as usize;
+                self.sandbox_check(src, len)?;
+                self.sandbox_check(dest, len)?;
+                self.memory.copy_within(src..src + len, dest);
+                self.mark_touched(dest, len);
+                self.program_counter + instruction.len()
+            }
+            MEMSET => {
+                let dest = self.memory_fetch(0, ADDRESS_SIZE, address_decode(&instruction[1..5]))? as usize;
+                let value = self.memory_fetch(0, 1, address_decode(&instruction[5..9]))? as u8;
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[9..13]))? as usize;
+                self.sandbox_check(dest, len)?;
+                self.memory[dest..dest + len].fill(value);
+                self.mark_touched(dest, len);
+                self.program_counter + instruction.len()
+            }
+            SWAP => {
+                let size = size_decode(instruction[1]);
+                let first = address_decode(&instruction[2..6]);
+                let second = address_decode(&instruction[6..10]);
+                let a = self.memory_fetch(0, size, first)?;
+                let b = self.memory_fetch(0, size, second)?;
+                self.memory_write(0, size, first, b)?;
+                self.memory_write(0, size, second, a)?;
+                next
+            }
+            BSWAP => {
+                let size = size_decode(instruction[1]);
+                let value = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                // Reversing all 8 bytes moves the value to the top, so shift it back down
+                let swapped = value.swap_bytes().checked_shr(64 - size as u32 * 8).unwrap_or(0);
+                self.memory_write(0, size, address_decode(&instruction[10..14]), swapped)?;
+                next
+            }
+            POPCNT | CLZ | CTZ => {
+                let size = size_decode(instruction[1]);
+                let value = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let bits = size as u32 * 8;
+                let count = match opcode {
+                    POPCNT => value.count_ones(),
+                    // The value is zero-extended to 64 bits, so don't count the padding
+                    CLZ => value.leading_zeros() - (64 - bits),
+                    _ => value.trailing_zeros().min(bits),
+                };
+                self.memory_write(0, size, address_decode(&instruction[10..14]), count as u64)?;
+                next
+            }
+            ROTL | ROTR => {
+                let size = size_decode(instruction[1]);
+                let a = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let b = self.memory_fetch(0, size, address_decode(&instruction[6..10]))?;
+                // Rotate within the operand size, a right rotation being a left rotation by the rest
+                let bits = size as u32 * 8;
+                let amount = ((b & 0x3F) as u32).checked_rem(bits).unwrap_or(0);
+                let amount = if opcode == ROTR && amount != 0 { bits - amount } else { amount };
+                let result = match amount {
+                    0 => a,
+                    amount => (a << amount) | (a >> (bits - amount)),
+                };
+                self.memory_write(0, size, address_decode(&instruction[10..14]), result)?;
+                next
+            }
+            INC | DEC => {
+                let size = size_decode(instruction[1]);
+                let address = address_decode(&instruction[2..6]);
+                let value = self.memory_fetch(0, size, address)?;
+                let result = if opcode == INC { value.wrapping_add(1) } else { value.wrapping_sub(1) };
+                self.memory_write(0, size, address, result)?;
+                next
+            }
+            NEG => {
+                let size = size_decode(instruction[1]);
+                let value = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                self.memory_write(0, size, address_decode(&instruction[10..14]), value.wrapping_neg())?;
+                next
+            }
+            ABS => {
+                let size = size_decode(instruction[1]);
+                let value = sign_extend(self.memory_fetch(0, size, address_decode(&instruction[2..6]))?, size);
+                // The smallest value of the operand size has no positive counterpart that fits
+                if value != 0 && value == i64::MIN >> (64 - size as u32 * 8) {
+                    return Err(TransientError::Overflow);
+                }
+                self.memory_write(0, size, address_decode(&instruction[10..14]), value.unsigned_abs())?;
+                next
+            }
+            ASSERT | NASSERT => {
+                if self.debug_assertions {
+                    let size = size_decode(instruction[1]);
+                    let address = address_decode(&instruction[2..6]);
+                    let value = self.memory_fetch(0, size, address)?;
+                    if (value == 0) == (opcode == ASSERT) {
+                        return Err(TransientError::AssertionFailed { addr: address as usize, pc: self.program_counter });
+                    }
+                }
+                next
+            }
+            TRAP => {
+                // The hook was called with this instruction by step()
+                if self.pre_hook.is_some() {
+                    self.mode = TransientMode::PAUSED;
+                }
+                self.program_counter + instruction.len()
+            }
+            BOUNDS => {
+                let size = size_decode(instruction[1]);
+                let value = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let low = self.memory_fetch(0, size, address_decode(&instruction[6..10]))?;
+                let high = self.memory_fetch(0, size, address_decode(&instruction[10..14]))?;
+                let result = (low <= value && value <= high) as u64;
+                self.memory_write(0, size, address_decode(&instruction[14..18]), result)?;
+                next
+            }
+            SCGT | SCLT | SEQU => {
+                let size = size_decode(instruction[1]);
+                let a = sign_extend(self.memory_fetch(0, size, address_decode(&instruction[2..6]))?, size);
+                let b = sign_extend(self.memory_fetch(0, size, address_decode(&instruction[6..10]))?, size);
+                let result = match opcode {
+                    SCGT => a > b,
+                    SCLT => a < b,
+                    _ => a == b,
+                };
+                self.memory_write(0, size, address_decode(&instruction[10..14]), result as u64)?;
+                next
+            }
+            ADDO | SUBO | MULO => {
+                let size = size_decode(instruction[1]);
+                let a = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let b = self.memory_fetch(0, size, address_decode(&instruction[6..10]))?;
+                let (result, wrapped) = match opcode {
+                    ADDO => a.overflowing_add(b),
+                    SUBO => a.overflowing_sub(b),
+                    _ => a.overflowing_mul(b),
+                };
+                // Results can also overflow the operand size without wrapping the 64 bit value
+                let overflow = wrapped || result > u64::MAX.checked_shr(64 - size as u32 * 8).unwrap_or(0);
+                self.memory_write(0, size, address_decode(&instruction[10..14]), result)?;
+                self.memory_write(0, size, address_decode(&instruction[14..18]), overflow as u64)?;
+                next
+            }
+            SADD | SSUB | SMUL => {
+                let size = size_decode(instruction[1]);
+                let a = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let b = self.memory_fetch(0, size, address_decode(&instruction[6..10]))?;
+                let unused = 64 - size as u32 * 8;
+                let result = if self.signed_arithmetic {
+                    let (a, b) = (sign_extend(a, size), sign_extend(b, size));
+                    let max = i64::MAX.checked_shr(unused).unwrap_or(0);
+                    let result = match opcode {
+                        SADD => a.saturating_add(b),
+                        SSUB => a.saturating_sub(b),
+                        _ => a.saturating_mul(b),
+                    };
+                    result.clamp(!max, max) as u64
+                } else {
+                    let result = match opcode {
+                        SADD => a.saturating_add(b),
+                        SSUB => a.saturating_sub(b),
+                        _ => a.saturating_mul(b),
+                    };
+                    result.min(u64::MAX.checked_shr(unused).unwrap_or(0))
+                };
+                self.memory_write(0, size, address_decode(&instruction[10..14]), result)?;
+                next
+            }
+            MIN | MAX => {
+                let size = size_decode(instruction[1]);
+                let a = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let b = self.memory_fetch(0, size, address_decode(&instruction[6..10]))?;
+                let a_first = if self.signed_arithmetic {
+                    sign_extend(a, size) < sign_extend(b, size)
+                } else {
+                    a < b
+                };
+                let result = if a_first == (opcode == MIN) { a } else { b };
+                self.memory_write(0, size, address_decode(&instruction[10..14]), result)?;
+                next
+            }
+            MOD => {
+                let size = size_decode(instruction[1]);
+                let a = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let b = self.memory_fetch(0, size, address_decode(&instruction[6..10]))?;
+                let (a, b) = (sign_extend(a, size), sign_extend(b, size));
+                if b == 0 {
+                    return Err(TransientError::DivisionByZero);
+                }
+                self.memory_write(0, size, address_decode(&instruction[10..14]), a.wrapping_rem_euclid(b) as u64)?;
+                next
+            }
+            IMM => {
+                let size = size_decode(instruction[1]);
+                if size > 8 {
+                    return Err(TransientError::InvalidOperand("Operand size must be at most 8 bytes"));
+                }
+                let value = u64::from_be_bytes(u64_pad_be(&instruction[2..][..size as usize]));
+                self.memory_write(0, size, address_decode(&instruction[2 + size as usize..]), value)?;
+                next
+            }
+            RANDOM => {
+                let size = size_decode(instruction[1]);
+                self.rng_state = xorshift64(self.rng_state);
+                self.memory_write(0, size, address_decode(&instruction[10..14]), self.rng_state)?;
+                next
+            }
+            #[cfg(feature = "std")]
+            CLOCK => {
+                let size = size_decode(instruction[1]);
+                let elapsed = self.start_time.elapsed().as_nanos() as u64;
+                self.memory_write(0, size, address_decode(&instruction[10..14]), elapsed)?;
+                next
+            }
+            #[cfg(not(feature = "std"))]
+            CLOCK => return Err(TransientError::InvalidOperand("CLOCK needs the std feature")),
+            STRLEN => {
+                let length = self.string_at(address_decode(&instruction[1..5]) as usize)?.len();
+                self.memory_write(0, ADDRESS_SIZE, address_decode(&instruction[5..9]), length as u64)?;
+                self.program_counter + instruction.len()
+            }
+            STRCMP => {
+                let a = self.string_at(address_decode(&instruction[1..5]) as usize)?;
+                let b = self.string_at(address_decode(&instruction[5..9]) as usize)?;
+                let result = a.cmp(b) as i8;
+                self.memory_write(0, 1, address_decode(&instruction[9..13]), result as u8 as u64)?;
+                self.program_counter + instruction.len()
+            }
+            MEMMOVE => {
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))? as usize;
+                let src = address_decode(&instruction[5..9]) as usize;
+                let dest = address_decode(&instruction[9..13]) as usize;
+                self.sandbox_check(src, len)?;
+                self.sandbox_check(dest, len)?;
+                // copy_within picks the correct direction for overlapping regions
+                self.memory.copy_within(src..src + len, dest);
+                self.mark_touched(dest, len);
+                self.program_counter + instruction.len()
+            }
+            PRTMM => {
+                let report = self.format_memory_map();
+                // Without stderr, the report goes to the output device
+                #[cfg(feature = "std")]
+                eprint!("{}", report);
+                #[cfg(not(feature = "std"))]
+                self.write_output(report.as_bytes())?;
+                self.program_counter + instruction.len()
+            }
+            SYSCALL => {
+                let result = self.syscall(address_decode(&instruction[1..5]))?;
+                self.memory_write(0, ADDRESS_SIZE, SYSCALL_ARGS[0], result)?;
+                self.program_counter + instruction.len()
+            }
+            SAVEFP => {
+                self.memory_write(0, ADDRESS_SIZE, address_decode(&instruction[1..5]), self.frame_pointer as u64)?;
+                self.program_counter + instruction.len()
+            }
+            RESTFP => {
+                self.frame_pointer = self.memory_fetch(0, ADDRESS_SIZE, address_decode(&instruction[1..5]))? as usize;
+                self.program_counter + instruction.len()
+            }
+            SWITCH => {
+                let value = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))?;
+                let table_base = address_decode(&instruction[5..9]);
+                let table_len = self.memory_fetch(0, 8, address_decode(&instruction[9..13]))? as u32;
+                let default = address_decode(&instruction[13..17]) as usize;
+                // Each entry is an 8 byte value followed by a 4 byte target
+                let (mut low, mut high) = (0, table_len);
+                while low < high {
+                    let middle = low + (high - low) / 2;
+                    let entry = self.address_offset(table_base, middle as u64 * (8 + ADDRESS_SIZE as u64))?;
+                    match self.memory_fetch(0, 8, entry)?.cmp(&value) {
+                        core::cmp::Ordering::Less => low = middle + 1,
+                        core::cmp::Ordering::Greater => high = middle,
+                        core::cmp::Ordering::Equal => return Ok(self.memory_fetch(0, ADDRESS_SIZE, self.address_offset(entry, 8)?)? as usize),
+                    }
+                }
+                default
+            }
+            COSAVE => {
+                let context = address_decode(&instruction[1..5]);
+                let next = self.program_counter + instruction.len();
+                self.memory_write(0, ADDRESS_SIZE, context, next as u64)?;
+                self.memory_write(0, ADDRESS_SIZE, self.address_offset(context, ADDRESS_SIZE as u64)?, self.stack_pointer as u64)?;
+                next
+            }
+            CORESTORE => {
+                let context = address_decode(&instruction[1..5]);
+                self.stack_pointer = self.memory_fetch(0, ADDRESS_SIZE, self.address_offset(context, ADDRESS_SIZE as u64)?)? as usize;
+                self.memory_fetch(0, ADDRESS_SIZE, context)? as usize
+            }
+            PROFREGION => {
+                self.profile_region = address_decode(&instruction[1..5]);
+                self.program_counter + instruction.len()
+            }
+            REPEAT => {
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))?;
+                let target = self.program_counter + instruction.len();
+                self.program_counter = target;
+                let mut repeated = self.fetch_instruction()?;
+                if changes_control_flow(repeated[0]) {
+                    return Err(TransientError::InvalidOperand("REPEAT can't repeat a jump or halt"));
+                }
+                let next = target + repeated.len();
+                for iteration in 0..count {
+                    if iteration > 0 {
+                        self.advance_operands(&mut repeated)?;
+                    }
+                    self.program_counter = target;
+                    self.execute_counted(&repeated)?;
+                    self.call_post_hook(&repeated, next);
+                    // SYSCALL 0 halts the processor
+                    if matches!(self.mode, TransientMode::HALTED(_)) {
+                        break;
+                    }
+                }
+                next
+            }
+            SETPRED => {
+                self.condition_register = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))? as u8;
+                self.program_counter + instruction.len()
+            }
+            CLRPRED => {
+                self.condition_register = 1;
+                self.program_counter + instruction.len()
+            }
+            ADDMOD | MULMOD => {
+                let size = size_decode(instruction[1]);
+                let src1 = self.memory_fetch(0, size, address_decode(&instruction[2..6]))? as u128;
+                let src2 = self.memory_fetch(0, size, address_decode(&instruction[6..10]))? as u128;
+                let modulus = self.memory_fetch(0, size, address_decode(&instruction[10..14]))? as u128;
+                if modulus == 0 {
+                    return Err(TransientError::DivisionByZero);
+                }
+                let result = match opcode {
+                    ADDMOD => (src1 + src2) % modulus,
+                    _ => (src1 * src2) % modulus,
+                };
+                self.memory_write(0, size, address_decode(&instruction[14..18]), result as u64)?;
+                self.program_counter + instruction.len()
+            }
+            MODPOW => {
+                let size = size_decode(instruction[1]);
+                let base = self.memory_fetch(0, size, address_decode(&instruction[2..6]))? as u128;
+                let exponent = self.memory_fetch(0, size, address_decode(&instruction[6..10]))?;
+                let modulus = self.memory_fetch(0, size, address_decode(&instruction[10..14]))? as u128;
+                if modulus < 2 {
+                    return Err(TransientError::InvalidOperand("Modular exponentiation requires a modulus of at least 2"));
+                }
+                // Left-to-right binary exponentiation, u128 products cannot overflow
+                let mut result: u128 = 1;
+                for bit in (0..64 - exponent.leading_zeros()).rev() {
+                    result = result * result % modulus;
+                    if exponent >> bit & 1 == 1 {
+                        result = result * (base % modulus) % modulus;
+                    }
+                }
+                self.memory_write(0, size, address_decode(&instruction[14..18]), result as u64)?;
+                self.program_counter + instruction.len()
+            }
+            INVMOD => {
+                let size = size_decode(instruction[1]);
+                let value = self.memory_fetch(0, size, address_decode(&instruction[2..6]))? as i128;
+                let modulus = self.memory_fetch(0, size, address_decode(&instruction[6..10]))? as i128;
+                if modulus == 0 {
+                    return Err(TransientError::DivisionByZero);
+                }
+                // Extended Euclidean algorithm, tracking the coefficient of value
+                let (mut r0, mut r1) = (modulus, value % modulus);
+                let (mut t0, mut t1) = (0i128, 1i128);
+                while r1 != 0 {
+                    let quotient = r0 / r1;
+                    (r0, r1) = (r1, r0 - quotient * r1);
+                    (t0, t1) = (t1, t0 - quotient * t1);
+                }
+                let exists = r0 == 1;
+                let inverse = if exists { t0.rem_euclid(modulus) } else { 0 };
+                self.memory_write(0, size, address_decode(&instruction[10..14]), inverse as u64)?;
+                self.memory_write(0, size, address_decode(&instruction[14..18]), exists as u64)?;
+                self.program_counter + instruction.len()
+            }
+            XORSHIFT64 => {
+                let state_address = address_decode(&instruction[1..5]);
+                let mut state = self.memory_fetch(0, 8, state_address)?;
+                if state == 0 {
+                    return Err(TransientError::InvalidOperand("Xorshift state must be non-zero"));
+                }
+                state = xorshift64(state);
+                self.memory_write(0, 8, state_address, state)?;
+                self.memory_write(0, 8, address_decode(&instruction[5..9]), state)?;
+                self.program_counter + instruction.len()
+            }
+            CHACHA20BLK => {
+                let state_base = address_decode(&instruction[1..5]) as usize;
+                let out_base = address_decode(&instruction[5..9]) as usize;
+                self.sandbox_check(state_base, 64)?;
+                self.sandbox_check(out_base, 64)?;
+                let mut state = [0u32; 16];
+                for (index, word) in state.iter_mut().enumerate() {
+                    *word = u32::from_le_bytes(self.memory[state_base + index * 4..][..4].try_into().expect("[Halt]: ChaCha20 failed: Couldn't parse internal memory slice"));
+                }
+                let block = chacha20_block(&state);
+                for (index, word) in block.iter().enumerate() {
+                    self.memory[out_base + index * 4..][..4].copy_from_slice(&word.to_le_bytes());
+                    self.mark_touched(out_base + index * 4, 4);
+                }
+                self.program_counter + instruction.len()
+            }
+            SHA256COMP => {
+                let state_base = address_decode(&instruction[1..5]) as usize;
+                let block_base = address_decode(&instruction[5..9]) as usize;
+                self.sandbox_check(state_base, 32)?;
+                self.sandbox_check(block_base, 64)?;
+                let mut state = [0u32; 8];
+                for (index, word) in state.iter_mut().enumerate() {
+                    *word = u32::from_be_bytes(self.memory[state_base + index * 4..][..4].try_into().expect("[Halt]: SHA-256 failed: Couldn't parse internal memory slice"));
+                }
+                let block: [u8; 64] = self.memory[block_base..][..64].try_into().expect("[Halt]: SHA-256 failed: Couldn't parse internal memory slice");
+                sha256_compress(&mut state, &block);
+                for (index, word) in state.iter().enumerate() {
+                    self.memory[state_base + index * 4..][..4].copy_from_slice(&word.to_be_bytes());
+                    self.mark_touched(state_base + index * 4, 4);
+                }
+                self.program_counter + instruction.len()
+            }
+            POPSTACK => {
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))? as usize;
+                if count > TRANSIENT_MEM_MAX.saturating_sub(self.stack_pointer) {
+                    return Err(TransientError::StackUnderflow);
+                }
+                self.stack_pointer += count;
+                self.program_counter + instruction.len()
+            }
+            PEEK => {
+                let size = size_decode(instruction[1]) as usize;
+                let dest = address_decode(&instruction[2..6]) as usize;
+                if size > TRANSIENT_MEM_MAX.saturating_sub(self.stack_pointer) {
+                    return Err(TransientError::StackUnderflow);
+                }
+                self.sandbox_check(self.stack_pointer, size)?;
+                self.sandbox_check(dest, size)?;
+                self.memory.copy_within(self.stack_pointer..self.stack_pointer + size, dest);
+                self.mark_touched(dest, size);
+                self.program_counter + instruction.len()
+            }
+            PUSHPC => {
+                let next = self.program_counter + instruction.len();
+                self.stack_push(ADDRESS_SIZE, next as u64)?;
+                next
+            }
+            POPPC => self.stack_pop(ADDRESS_SIZE)? as usize,
+            VCMPEQ8 | VCMPGT8 => {
+                let src1 = address_decode(&instruction[1..5]) as usize;
+                let src2 = address_decode(&instruction[5..9]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[9..13]))? as usize;
+                let dest = address_decode(&instruction[13..17]) as usize;
+                self.sandbox_check(src1, count)?;
+                self.sandbox_check(src2, count)?;
+                self.sandbox_check(dest, count)?;
+                for index in 0..count {
+                    let (a, b) = (self.memory[src1 + index], self.memory[src2 + index]);
+                    self.memory[dest + index] = match opcode {
+                        VCMPEQ8 => (a == b) as u8,
+                        _ => (a > b) as u8,
+                    };
+                }
+                self.mark_touched(dest, count);
+                self.program_counter + instruction.len()
+            }
+            DOTPROD => {
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))? as usize;
+                let src1 = address_decode(&instruction[5..9]) as usize;
+                let src2 = address_decode(&instruction[9..13]) as usize;
+                self.sandbox_check(src1, len)?;
+                self.sandbox_check(src2, len)?;
+                let sum = self.memory[src1..][..len]
+                    .iter()
+                    .zip(&self.memory[src2..][..len])
+                    .fold(0u64, |sum, (a, b)| sum.wrapping_add((*a as u32 * *b as u32) as u64));
+                self.memory_write(0, 8, address_decode(&instruction[13..17]), sum)?;
+                self.program_counter + instruction.len()
+            }
+            TLOOKUP => {
+                let index = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))? as usize;
+                let table_base = address_decode(&instruction[5..9]) as usize;
+                let elem_size = instruction[9] as usize;
+                let dest = address_decode(&instruction[10..14]) as usize;
+                let element = index
+                    .checked_mul(elem_size)
+                    .and_then(|x| x.checked_add(table_base))
+                    .ok_or(TransientError::InvalidOperand("Table lookup index out of bounds"))?;
+                self.sandbox_check(element, elem_size)?;
+                self.sandbox_check(dest, elem_size)?;
+                self.memory.copy_within(element..element + elem_size, dest);
+                self.mark_touched(dest, elem_size);
+                self.program_counter + instruction.len()
+            }
+            SETOUT => {
+                let device = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))? as u32;
+                #[cfg(feature = "std")]
+                let registered = self.output_devices.contains_key(&device);
+                #[cfg(not(feature = "std"))]
+                let registered = false;
+                if device != 0 && !registered {
+                    return Err(TransientError::UnknownDevice(device));
+                }
+                self.output_device = device;
+                self.program_counter + instruction.len()
+            }
+            SETIN => {
+                let device = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))? as u32;
+                #[cfg(feature = "std")]
+                let registered = self.input_devices.contains_key(&device);
+                #[cfg(not(feature = "std"))]
+                let registered = false;
+                if device != 0 && !registered {
+                    return Err(TransientError::UnknownDevice(device));
+                }
+                self.input_device = device;
+                self.program_counter + instruction.len()
+            }
+            BLOOPN => {
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))?;
+                let body = address_decode(&instruction[5..9]) as usize;
+                let next = self.program_counter + instruction.len();
+                if count == 0 {
+                    next
+                } else {
+                    self.loops.push((body, count, next));
+                    body
+                }
+            }
+            LEND => {
+                let Some(active) = self.loops.last_mut() else {
+                    return Err(TransientError::InvalidOperand("LEND executed outside of a BLOOPN loop"));
+                };
+                active.1 -= 1;
+                if active.1 > 0 {
+                    active.0
+                } else {
+                    let (_, _, next) = self.loops.pop().unwrap();
+                    next
+                }
+            }
+            RFIND => {
+                let base = address_decode(&instruction[1..5]) as usize;
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let value = self.memory_fetch(0, 1, address_decode(&instruction[9..13]))? as u8;
+                self.sandbox_check(base, len)?;
+                let offset = self.memory[base..][..len].iter().rposition(|&x| x == value).unwrap_or(len);
+                self.memory_write(0, 8, address_decode(&instruction[13..17]), offset as u64)?;
+                self.program_counter + instruction.len()
+            }
+            SPLITB => {
+                let size = size_decode(instruction[1]) as usize;
+                let value = self.memory_fetch(0, size as u8, address_decode(&instruction[2..6]))?;
+                let dest = address_decode(&instruction[6..10]) as usize;
+                self.sandbox_check(dest, size)?;
+                self.memory[dest..][..size].copy_from_slice(&value.to_be_bytes()[8 - size..]);
+                self.mark_touched(dest, size);
+                self.program_counter + instruction.len()
+            }
+            JOINB => {
+                let size = size_decode(instruction[1]) as usize;
+                let src = address_decode(&instruction[2..6]) as usize;
+                self.sandbox_check(src, size)?;
+                let value = self.memory[src..][..size].iter().fold(0u64, |acc, &x| acc << 8 | x as u64);
+                self.memory_write(0, size as u8, address_decode(&instruction[6..10]), value)?;
+                self.program_counter + instruction.len()
+            }
+            INTERLEAVE => {
+                let ch1 = address_decode(&instruction[1..5]) as usize;
+                let ch2 = address_decode(&instruction[5..9]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[9..13]))? as usize;
+                let dest = address_decode(&instruction[13..17]) as usize;
+                self.sandbox_check(ch1, count)?;
+                self.sandbox_check(ch2, count)?;
+                let length = self.region_length(dest, count, 2)?;
+                self.sandbox_check(dest, length)?;
+                let interleaved: Vec<u8> = self.memory[ch1..][..count].iter()
+                    .zip(&self.memory[ch2..][..count])
+                    .flat_map(|(&a, &b)| [a, b])
+                    .collect();
+                self.memory[dest..][..length].copy_from_slice(&interleaved);
+                self.mark_touched(dest, length);
+                self.program_counter + instruction.len()
+            }
+            DEINTERLEAVE => {
+                let src = address_decode(&instruction[1..5]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let ch1 = address_decode(&instruction[9..13]) as usize;
+                let ch2 = address_decode(&instruction[13..17]) as usize;
+                let length = self.region_length(src, count, 2)?;
+                self.sandbox_check(src, length)?;
+                self.sandbox_check(ch1, count)?;
+                self.sandbox_check(ch2, count)?;
+                let interleaved = self.memory[src..][..length].to_vec();
+                for (index, pair) in interleaved.chunks_exact(2).enumerate() {
+                    self.memory[ch1 + index] = pair[0];
+                    self.mark_touched(ch1 + index, 1);
+                    self.memory[ch2 + index] = pair[1];
+                    self.mark_touched(ch2 + index, 1);
+                }
+                self.program_counter + instruction.len()
+            }
+            RLEENC => {
+                let src = address_decode(&instruction[1..5]) as usize;
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let dest = address_decode(&instruction[9..13]) as usize;
+                let capacity = self.memory_fetch(0, 8, address_decode(&instruction[13..17]))? as usize;
+                self.sandbox_check(src, len)?;
+                let mut encoded: Vec<u8> = Vec::new();
+                for &byte in &self.memory[src..][..len] {
+                    match encoded.len().checked_sub(2).map(|last| &mut encoded[last..]) {
+                        Some([count, value]) if *value == byte && *count < 255 => *count += 1,
+                        _ => encoded.extend_from_slice(&[1, byte]),
+                    }
+                }
+                if encoded.len() > capacity {
+                    return Err(TransientError::InvalidOperand("Run-length encoded data does not fit in the destination"));
+                }
+                self.sandbox_check(dest, encoded.len())?;
+                self.memory[dest..][..encoded.len()].copy_from_slice(&encoded);
+                self.mark_touched(dest, encoded.len());
+                self.memory_write(0, 8, address_decode(&instruction[17..21]), encoded.len() as u64)?;
+                self.program_counter + instruction.len()
+            }
+            RLEDEC => {
+                let src = address_decode(&instruction[1..5]) as usize;
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let dest = address_decode(&instruction[9..13]) as usize;
+                let capacity = self.memory_fetch(0, 8, address_decode(&instruction[13..17]))? as usize;
+                self.sandbox_check(src, len)?;
+                if !len.is_multiple_of(2) {
+                    return Err(TransientError::InvalidOperand("Run-length encoded input ends in the middle of a (count, value) pair"));
+                }
+                let mut decoded: Vec<u8> = Vec::new();
+                for pair in self.memory[src..][..len].chunks_exact(2) {
+                    decoded.extend(core::iter::repeat_n(pair[1], pair[0] as usize));
+                }
+                if decoded.len() > capacity {
+                    return Err(TransientError::InvalidOperand("Run-length decoded data does not fit in the destination"));
+                }
+                self.sandbox_check(dest, decoded.len())?;
+                self.memory[dest..][..decoded.len()].copy_from_slice(&decoded);
+                self.mark_touched(dest, decoded.len());
+                self.memory_write(0, 8, address_decode(&instruction[17..21]), decoded.len() as u64)?;
+                self.program_counter + instruction.len()
+            }
+            DELTAENC => {
+                let src = address_decode(&instruction[1..5]) as usize;
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let dest = address_decode(&instruction[9..13]) as usize;
+                self.sandbox_check(src, len)?;
+                self.sandbox_check(dest, len)?;
+                let encoded: Vec<u8> = self.memory[src..][..len].iter()
+                    .scan(0u8, |previous, &x| Some(x.wrapping_sub(core::mem::replace(previous, x))))
+                    .collect();
+                self.memory[dest..][..len].copy_from_slice(&encoded);
+                self.mark_touched(dest, len);
+                self.program_counter + instruction.len()
+            }
+            DELTADEC => {
+                let src = address_decode(&instruction[1..5]) as usize;
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let dest = address_decode(&instruction[9..13]) as usize;
+                self.sandbox_check(src, len)?;
+                self.sandbox_check(dest, len)?;
+                let decoded: Vec<u8> = self.memory[src..][..len].iter()
+                    .scan(0u8, |previous, &x| {
+                        *previous = previous.wrapping_add(x);
+                        Some(*previous)
+                    })
+                    .collect();
+                self.memory[dest..][..len].copy_from_slice(&decoded);
+                self.mark_touched(dest, len);
+                self.program_counter + instruction.len()
+            }
+            NIBPK => {
+                let src = address_decode(&instruction[1..5]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let dest = address_decode(&instruction[9..13]) as usize;
+                self.sandbox_check(src, count)?;
+                self.sandbox_check(dest, count.div_ceil(2))?;
+                let packed: Vec<u8> = self.memory[src..][..count]
+                    .chunks(2)
+                    .map(|pair| (pair[0] & 0x0F) << 4 | pair.get(1).map_or(0, |x| x & 0x0F))
+                    .collect();
+                self.memory[dest..][..packed.len()].copy_from_slice(&packed);
+                self.mark_touched(dest, packed.len());
+                self.program_counter + instruction.len()
+            }
+            NIBUPK => {
+                let src = address_decode(&instruction[1..5]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let dest = address_decode(&instruction[9..13]) as usize;
+                self.sandbox_check(src, count.div_ceil(2))?;
+                self.sandbox_check(dest, count)?;
+                let unpacked: Vec<u8> = self.memory[src..][..count.div_ceil(2)].iter()
+                    .flat_map(|&x| [x >> 4, x & 0x0F])
+                    .take(count)
+                    .collect();
+                self.memory[dest..][..count].copy_from_slice(&unpacked);
+                self.mark_touched(dest, count);
+                self.program_counter + instruction.len()
+            }
+            FIBSTEP => {
+                let a_addr = address_decode(&instruction[1..5]);
+                let b_addr = address_decode(&instruction[5..9]);
+                let a = self.memory_fetch(0, 8, a_addr)?;
+                let b = self.memory_fetch(0, 8, b_addr)?;
+                self.memory_write(0, 8, a_addr, b)?;
+                self.memory_write(0, 8, b_addr, a.wrapping_add(b))?;
+                self.program_counter + instruction.len()
+            }
+            BSEARCH => {
+                let base = address_decode(&instruction[1..5]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let target = self.memory_fetch(0, 1, address_decode(&instruction[9..13]))? as u8;
+                self.sandbox_check(base, count)?;
+                let index = match self.memory[base..][..count].binary_search(&target) {
+                    Ok(index) => index as u64,
+                    Err(insertion_point) => !(insertion_point as u64),
+                };
+                self.memory_write(0, 8, address_decode(&instruction[13..17]), index)?;
+                self.program_counter + instruction.len()
+            }
+            QSORT => {
+                let base = address_decode(&instruction[1..5]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let element_size = self.memory_fetch(0, 8, address_decode(&instruction[9..13]))? as usize;
+                let descending = self.memory_fetch(0, 8, address_decode(&instruction[13..17]))? != 0;
+                if element_size == 0 {
+                    return Err(TransientError::InvalidOperand("Sort element size must be at least 1 byte"));
+                }
+                let length = self.region_length(base, count, element_size)?;
+                self.sandbox_check(base, length)?;
+                let mut elements: Vec<Vec<u8>> = self.memory[base..][..length]
+                    .chunks_exact(element_size)
+                    .map(|x| x.to_vec())
+                    .collect();
+                if descending {
+                    elements.sort_unstable_by(|a, b| b.cmp(a));
+                } else {
+                    elements.sort_unstable();
+                }
+                self.memory[base..][..length].copy_from_slice(&elements.concat());
+                self.mark_touched(base, length);
+                self.program_counter + instruction.len()
+            }
+            UNIQSORT => {
+                let base = address_decode(&instruction[1..5]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                self.sandbox_check(base, count)?;
+                let mut unique = self.memory[base..][..count].to_vec();
+                unique.sort_unstable();
+                unique.dedup();
+                self.memory[base..][..unique.len()].copy_from_slice(&unique);
+                self.mark_touched(base, unique.len());
+                self.memory_write(0, 8, address_decode(&instruction[9..13]), unique.len() as u64)?;
+                self.program_counter + instruction.len()
+            }
+            PCTILE => {
+                let base = address_decode(&instruction[1..5]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let percentile = self.memory_fetch(0, 8, address_decode(&instruction[9..13]))? as usize;
+                if count == 0 {
+                    return Err(TransientError::InvalidOperand("Percentile of an empty array"));
+                }
+                if percentile > 100 {
+                    return Err(TransientError::InvalidOperand("Percentile must be between 0 and 100"));
+                }
+                self.sandbox_check(base, count)?;
+                let mut sorted = self.memory[base..][..count].to_vec();
+                sorted.sort_unstable();
+                let rank = (count * percentile).div_ceil(100).max(1);
+                self.memory_write(0, 8, address_decode(&instruction[13..17]), sorted[rank - 1] as u64)?;
+                self.program_counter + instruction.len()
+            }
+            HISTO => {
+                let src = address_decode(&instruction[1..5]) as usize;
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let dest = address_decode(&instruction[9..13]) as usize;
+                self.sandbox_check(src, len)?;
+                self.sandbox_check(dest, 512)?;
+                let mut counts = [0u16; 256];
+                for &byte in &self.memory[src..][..len] {
+                    counts[byte as usize] = counts[byte as usize].saturating_add(1);
+                }
+                for (index, count) in counts.iter().enumerate() {
+                    self.memory[dest + index * 2..][..2].copy_from_slice(&count.to_be_bytes());
+                    self.mark_touched(dest + index * 2, 2);
+                }
+                self.program_counter + instruction.len()
+            }
+            WSAMPLE => {
+                let base = address_decode(&instruction[1..5]) as usize;
+                let count = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let state_address = address_decode(&instruction[9..13]);
+                let state = self.memory_fetch(0, 8, state_address)?;
+                if state == 0 {
+                    return Err(TransientError::InvalidOperand("Weighted sample state must be non-zero"));
+                }
+                self.sandbox_check(base, count)?;
+                let weights = &self.memory[base..][..count];
+                let total: u64 = weights.iter().map(|&x| x as u64).sum();
+                if total == 0 {
+                    return Err(TransientError::InvalidOperand("Weighted sample weights are all 0"));
+                }
+                let state = xorshift64(state);
+                let mut remaining = state % total;
+                let mut index = 0;
+                while remaining >= weights[index] as u64 {
+                    remaining -= weights[index] as u64;
+                    index += 1;
+                }
+                self.memory_write(0, 8, state_address, state)?;
+                self.memory_write(0, 8, address_decode(&instruction[13..17]), index as u64)?;
+                self.program_counter + instruction.len()
+            }
+            BITMATMUL => {
+                let rows = self.memory_fetch(0, 8, address_decode(&instruction[1..5]))? as usize;
+                let cols = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let shared = self.memory_fetch(0, 8, address_decode(&instruction[9..13]))? as usize;
+                let mat1 = address_decode(&instruction[13..17]);
+                let mat2 = address_decode(&instruction[17..21]);
+                let dest = address_decode(&instruction[21..25]);
+                if rows > 64 || cols > 64 || shared > 64 {
+                    return Err(TransientError::InvalidOperand("Bit matrix dimensions must be at most 64"));
+                }
+                let col_mask = u64::MAX.checked_shr(64 - cols as u32).unwrap_or(0);
+                let mat2_rows = (0..shared)
+                    .map(|k| self.memory_fetch(0, 8, self.address_offset(mat2, k as u64 * 8)?))
+                    .collect::<Result<Vec<u64>, TransientError>>()?;
+                let product = (0..rows).map(|i| {
+                    let row = self.memory_fetch(0, 8, self.address_offset(mat1, i as u64 * 8)?)?;
+                    Ok((0..shared)
+                        .filter(|k| row >> k & 1 == 1)
+                        .fold(0, |acc, k| acc ^ mat2_rows[k]) & col_mask)
+                }).collect::<Result<Vec<u64>, TransientError>>()?;
+                for (i, row) in product.into_iter().enumerate() {
+                    self.memory_write(0, 8, self.address_offset(dest, i as u64 * 8)?, row)?;
+                }
+                self.program_counter + instruction.len()
+            }
+            POPCNTA => {
+                let base = address_decode(&instruction[1..5]) as usize;
+                let len = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                self.sandbox_check(base, len)?;
+                let chunks = self.memory[base..][..len].chunks_exact(8);
+                let tail: u32 = chunks.remainder().iter().map(|x| x.count_ones()).sum();
+                let total: u32 = chunks
+                    .map(|x| u64::from_ne_bytes(x.try_into().expect("[Halt]: Bit count failed: Couldn't parse internal memory slice")).count_ones())
+                    .sum();
+                self.memory_write(0, 4, address_decode(&instruction[9..13]), (total + tail) as u64)?;
+                self.program_counter + instruction.len()
+            }
+            DITHER => {
+                let src = address_decode(&instruction[1..5]) as usize;
+                let width = self.memory_fetch(0, 8, address_decode(&instruction[5..9]))? as usize;
+                let height = self.memory_fetch(0, 8, address_decode(&instruction[9..13]))? as usize;
+                let dest = address_decode(&instruction[13..17]) as usize;
+                let length = self.region_length(src, width, height)?;
+                self.sandbox_check(src, length)?;
+                self.sandbox_check(dest, length)?;
+                let mut pixels: Vec<i32> = self.memory[src..][..length].iter().map(|&x| x as i32).collect();
+                for y in 0..height {
+                    for x in 0..width {
+                        let old = pixels[y * width + x];
+                        let new = if old < 128 { 0 } else { 255 };
+                        pixels[y * width + x] = new;
+                        let error = old - new;
+                        let mut diffuse = |x: usize, y: usize, weight: i32| {
+                            if x < width && y < height {
+                                pixels[y * width + x] += error * weight / 16;
+                            }
+                        };
+                        diffuse(x + 1, y, 7);
+                        if x > 0 {
+                            diffuse(x - 1, y + 1, 3);
+                        }
+                        diffuse(x, y + 1, 5);
+                        diffuse(x + 1, y + 1, 1);
+                    }
+                }
+                for (index, pixel) in pixels.into_iter().enumerate() {
+                    self.memory[dest + index] = pixel as u8;
+                    self.mark_touched(dest + index, 1);
+                }
+                self.program_counter + instruction.len()
+            }
+            HLTE => {
+                let size = size_decode(instruction[1]);
+                let code = self.memory_fetch(0, size, address_decode(&instruction[2..6]))?;
+                let code = i32::try_from(code).map_err(|_| TransientError::InvalidOperand("Exit code must be at most 2147483647"))?;
+                self.mode = TransientMode::HALTED(Some(code));
+                self.program_counter
+            }
+            HLT => {
+                self.mode = TransientMode::HALTED(None);
+                self.program_counter
+            }
+            _ => return Err(TransientError::InvalidOpcode(opcode)),
+        })
+    }
+}
+
+/// Derives a nonzero generator seed from the current system time
+#[cfg(feature = "std")]
+fn time_seed() -> u64 {
+    let mut hasher = DefaultHasher::new();
+    SystemTime::now().hash(&mut hasher);
+    hasher.finish().max(1)
+}
+
+/// Without a system time, every processor starts from the same seed. See `set_rng_seed`.
+#[cfg(not(feature = "std"))]
+fn time_seed() -> u64 {
+    0x2545F4914F6CDD1D
+}
+
+/// Advances a xorshift64 generator state
+fn xorshift64(mut state: u64) -> u64 {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state
+}
+
+fn chacha20_quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// Computes the ChaCha20 block function as described in RFC 8439
+fn chacha20_block(state: &[u32; 16]) -> [u32; 16] {
+    let mut working = *state;
+    // 10 double rounds, each a column round followed by a diagonal round
+    for _ in 0..10 {
+        chacha20_quarter_round(&mut working, 0, 4, 8, 12);
+        chacha20_quarter_round(&mut working, 1, 5, 9, 13);
+        chacha20_quarter_round(&mut working, 2, 6, 10, 14);
+        chacha20_quarter_round(&mut working, 3, 7, 11, 15);
+        chacha20_quarter_round(&mut working, 0, 5, 10, 15);
+        chacha20_quarter_round(&mut working, 1, 6, 11, 12);
+        chacha20_quarter_round(&mut working, 2, 7, 8, 13);
+        chacha20_quarter_round(&mut working, 3, 4, 9, 14);
+    }
+    for (word, original) in working.iter_mut().zip(state) {
+        *word = word.wrapping_add(*original);
+    }
+    working
+}
+
+/// Applies the SHA-256 compression function to a state as described in FIPS 180-4
+fn sha256_compress(state: &mut [u32; 8], block: &[u8; 64]) {
+    // Prepare the message schedule
+    let mut schedule = [0u32; 64];
+    for (index, word) in block.chunks_exact(4).enumerate() {
+        schedule[index] = u32::from_be_bytes(word.try_into().expect("[Halt]: SHA-256 failed: Couldn't parse message block"));
+    }
+    for index in 16..64 {
+        let s0 = schedule[index - 15].rotate_right(7) ^ schedule[index - 15].rotate_right(18) ^ (schedule[index - 15] >> 3);
+        let s1 = schedule[index - 2].rotate_right(17) ^ schedule[index - 2].rotate_right(19) ^ (schedule[index - 2] >> 10);
+        schedule[index] = schedule[index - 16].wrapping_add(s0).wrapping_add(schedule[index - 7]).wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for index in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let temp1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(SHA256_ROUND_CONSTANTS[index]).wrapping_add(schedule[index]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(majority);
+        (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(temp1), c, b, a, temp1.wrapping_add(temp2));
+    }
+
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+fn u64_pad_be(data: &[u8]) -> [u8; 8] {
+    let mut padded = [0u8; 8];
+    padded[8 - data.len()..].copy_from_slice(data);
+    padded
+}
+
+/// Reinterprets the low `size` bytes of a value as a signed two's complement integer
+fn sign_extend(value: u64, size: u8) -> i64 {
+    match size {
+        0 => 0,
+        size => {
+            let unused = 64 - size as u32 * 8;
+            ((value << unused) as i64) >> unused
+        }
+    }
+}
+
+/// Returns whether the second byte of an instruction is a size byte that can carry flags
+fn has_size_byte(opcode: u8) -> bool {
+    matches!(opcode, MOV | ADD | SUB | MUL | DIVT | DIVR | REM | EQU | CGT | CLT | JMP | JIE | JNE | PUT_I | PUT_C | IMZ | HLT | ADDMOD | MULMOD | MODPOW | INVMOD | PEEK | SPLITB | JOINB | AND | OR | XOR | NOT | SHL | SHR | PUSH | POP | CGE | CLE | GET_I | GET_C | PUT_S | SWAP | BSWAP | POPCNT | CLZ | CTZ | ROTL | ROTR | INC | DEC | NEG | ABS | ASSERT | NASSERT | BOUNDS | SCGT | SCLT | SEQU | ADDO | SUBO | MULO | SADD | SSUB | SMUL | MIN | MAX | MOD | IMM | HLTE | RANDOM | CLOCK | PUT_H)
+}
+
+/// Strips the flags from a size byte
+fn size_decode(size: u8) -> u8 {
+    size & !(PREDICATE_FLAG | DELAY_SLOT_FLAG)
+}
+
+/// Returns whether an instruction moves the program counter anywhere but to the next instruction,
+/// or halts the processor. These can't be repeated by REPEAT or placed in a delay slot.
+fn changes_control_flow(opcode: u8) -> bool {
+    matches!(opcode, JMP | JIE | JNE | CALL | RET | SWITCH | CORESTORE | POPPC | BLOOPN | LEND | REPEAT | HLT | HLTE)
+}
+
+fn address_decode(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes.try_into().expect("[Halt]: Address decode failed: Couldn't parse instruction slice"))
+}
+
+#[cfg(all(test, feature = "std"))]
+pub(crate) mod tests {
+    use super::*;
+    use crate::compiler::compile;
+    use crate::image::read_symbols;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    /// Output that can still be read after it was handed to the processor
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl SharedBuffer {
+        pub fn contents(&self) -> String {
+            String::from_utf8_lossy(&self.0.borrow()).into_owned()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(data);
+            Ok(data.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Host I/O that records the output of a program and feeds it a fixed input
+    struct TestIO {
+        input: VecDeque<u8>,
+        output: SharedBuffer,
+    }
+
+    impl TransientIO for TestIO {
+        fn write_byte(&mut self, b: u8) {
+            self.output.0.borrow_mut().push(b);
+        }
+        fn read_byte(&mut self) -> Option<u8> {
+            self.input.pop_front()
+        }
+    }
+
+    /// A program that was compiled and run until it halted or failed
+    pub(crate) struct TestRun {
+        pub state: TransientState<65536>,
+        pub result: Result<(), TransientError>,
+        pub console: SharedBuffer,
+        symbols: Vec<(String, usize, usize)>,
+    }
+
+    impl TestRun {
+        /// Returns the address of a variable
+        pub fn address(&self, name: &str) -> usize {
+            self.symbols.iter().find(|(x, ..)| x == name).unwrap_or_else(|| panic!("no variable named {}", name)).1
+        }
+        /// Returns the current value of a variable
+        pub fn value(&self, name: &str) -> u64 {
+            let (_, address, size) = self.symbols.iter().find(|(x, ..)| x == name).unwrap_or_else(|| panic!("no variable named {}", name));
+            u64::from_be_bytes(u64_pad_be(&self.state.memory[*address..][..*size]))
+        }
+        /// Returns `length` bytes of memory starting at a variable
+        pub fn bytes(&self, name: &str, length: usize) -> &[u8] {
+            &self.state.memory[self.address(name)..][..length]
+        }
+    }
+
+    /// Compiles a program, with each line trimmed, and runs it with the given input
+    pub(crate) fn try_run_with_input(source: &str, input: &[u8]) -> TestRun {
+        try_run_with(source, input, |_| {})
+    }
+
+    /// Compiles a program, with each line trimmed, lets `setup` configure the processor and runs it
+    /// with the given input
+    pub(crate) fn try_run_with(source: &str, input: &[u8], setup: impl FnOnce(&mut TransientState<65536>)) -> TestRun {
+        let source: Vec<&str> = source.lines().map(|x| x.trim()).collect();
+        let image = compile(&source.join("\n")).unwrap_or_else(|error| panic!("{}", error));
+        let console = SharedBuffer::default();
+        let mut state = TransientState::new_with_io(TestIO { input: input.iter().copied().collect(), output: console.clone() });
+        state.set_rng_seed(1);
+        state.load_image(0, &image).unwrap();
+        setup(&mut state);
+        let result = state.run(0);
+        TestRun { state, result, console, symbols: read_symbols(&image).unwrap() }
+    }
+
+    /// Compiles and runs a program, which must halt without an error
+    pub(crate) fn run(source: &str) -> TestRun {
+        run_with(source, |_| {})
+    }
+
+    /// Compiles and runs a program after `setup` configured the processor, it must halt without an
+    /// error
+    pub(crate) fn run_with(source: &str, setup: impl FnOnce(&mut TransientState<65536>)) -> TestRun {
+        let run = try_run_with(source, &[], setup);
+        if let Err(error) = &run.result {
+            panic!("program failed: {}", error);
+        }
+        run
+    }
+
+    /// Declares consecutive byte variables holding `bytes`, the first of which is `$name`
+    pub(crate) fn declare_bytes(name: &str, bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .enumerate()
+            .map(|(index, byte)| match index {
+                0 => format!("set8 ${} {}\n", name, byte),
+                _ => format!("set8 ${}_{} {}\n", name, index, byte),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn operations_are_sorted_by_opcode() {
+        // find_operation binary searches the table
+        assert!(OPERATIONS.windows(2).all(|x| x[0].0 < x[1].0));
+    }
+
+    #[test]
+    fn memmove_copies_overlapping_regions_forward() {
+        let run = run(&format!("{}set64 $len 4\nmemmove $len $data $data_2\nhlt64", declare_bytes("data", &[1, 2, 3, 4, 5, 6, 7, 8])));
+        assert_eq!(run.bytes("data", 8), [1, 2, 1, 2, 3, 4, 7, 8]);
+    }
+
+    #[test]
+    fn memmove_copies_overlapping_regions_backward() {
+        let run = run(&format!("{}set64 $len 4\nmemmove $len $data_2 $data\nhlt64", declare_bytes("data", &[1, 2, 3, 4, 5, 6, 7, 8])));
+        assert_eq!(run.bytes("data", 8), [3, 4, 5, 6, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn memmove_rejects_overflowing_lengths() {
+        let run = try_run_with_input("set64 $len 18446744073709551615\nset8 $a 1\nset8 $b 2\nmemmove $len $a $b\nhlt64", &[]);
+        assert!(matches!(run.result, Err(TransientError::OutOfBoundsAccess { .. })));
+        assert_eq!(run.value("b"), 2);
+    }
+
+    #[test]
+    fn prtmm_lists_every_variable() {
+        let run = run("set64 $counter 3\nset64 $one 1\nsetstr $message \"hi\"\nprtmm\nadd64 $counter $one $counter\nhlt64");
+        let report = run.state.format_memory_map();
+        assert!(report.contains("counter: "), "{}", report);
+        assert!(report.contains("(8 bytes) = 4"), "{}", report);
+        // Values longer than 8 bytes are dumped instead of halting the processor
+        assert!(report.contains("message: "), "{}", report);
+        assert!(report.contains("(3 bytes) = \"hi\""), "{}", report);
+    }
+
+    #[test]
+    fn prtmm_dumps_long_variables() {
+        // Delta encoding turns the text into bytes that aren't printable
+        let run = run("setstr $message \"hello, world\"\nsetstr $encoded \"            \"\nset64 $len 12\ndeltaenc $message $len $encoded\nprtmm\nhlt64");
+        let report = run.state.format_memory_map();
+        assert!(report.contains("message: "), "{}", report);
+        assert!(report.contains("(13 bytes) = 68 FD 07 00 03 BD F4 57 F8 03 FA F8 00"), "{}", report);
+    }
+
+    #[test]
+    fn restfp_restores_a_corrupted_frame_pointer() {
+        let run = run("
+            set32 $initial 100
+            set32 $saved 0
+            set32 $garbage 1234
+            set32 $corrupted 0
+            restfp $initial
+            savefp $saved
+            restfp $garbage
+            savefp $corrupted
+            restfp $saved
+            hlt64
+        ");
+        assert_eq!(run.value("saved"), 100);
+        assert_eq!(run.value("corrupted"), 1234);
+        assert_eq!(run.state.frame_pointer, 100);
+    }
+
+    // Jump targets in the table are absolute: switch is 17 bytes long, every case is a 14 byte mov
+    // and a 14 byte halt
+    fn switch_on(value: u64) -> u64 {
+        run(&format!("
+            set64 $value {}
+            set64 $key_1 1
+            set32 $target_1 17
+            set64 $key_2 5
+            set32 $target_2 45
+            set64 $entries 2
+            set64 $one 1
+            set64 $five 5
+            set64 $other 99
+            set64 $result 0
+            switch $value $key_1 $entries #default
+            mov64 $one $result
+            hlt64
+            mov64 $five $result
+            hlt64
+            #default
+            mov64 $other $result
+            hlt64
+        ", value)).value("result")
+    }
+
+    #[test]
+    fn switch_jumps_to_the_matching_case() {
+        assert_eq!(switch_on(1), 1);
+        assert_eq!(switch_on(5), 5);
+    }
+
+    #[test]
+    fn switch_falls_back_to_the_default() {
+        assert_eq!(switch_on(0), 99);
+        assert_eq!(switch_on(3), 99);
+        assert_eq!(switch_on(6), 99);
+    }
+
+    #[test]
+    fn coroutines_alternate_through_cosave_and_corestore() {
+        // Each yield raises $yielding before saving its context, so the resumed side (which sees
+        // it cleared) can tell itself apart from the side that is about to switch away
+        let run = run("
+            set64 $context_a 0
+            set64 $context_b 0
+            set8 $yielding 0
+            set8 $zero 0
+            set8 $one 1
+            set64 $trace 0
+            set64 $ten 10
+            set64 $a 1
+            set64 $b 2
+            mul64 $trace $ten $trace
+            add64 $trace $a $trace
+            mov8 $one $yielding
+            cosave $context_a
+            jne8 #a_resumed_1 $yielding
+            mov8 $zero $yielding
+            jmp64 #b_start
+            #a_resumed_1
+            mul64 $trace $ten $trace
+            add64 $trace $a $trace
+            mov8 $one $yielding
+            cosave $context_a
+            jne8 #a_resumed_2 $yielding
+            mov8 $zero $yielding
+            corestore $context_b
+            #a_resumed_2
+            hlt64
+            #b_start
+            mul64 $trace $ten $trace
+            add64 $trace $b $trace
+            mov8 $one $yielding
+            cosave $context_b
+            jne8 #b_resumed_1 $yielding
+            mov8 $zero $yielding
+            corestore $context_a
+            #b_resumed_1
+            mul64 $trace $ten $trace
+            add64 $trace $b $trace
+            mov8 $one $yielding
+            cosave $context_b
+            jne8 #b_resumed_2 $yielding
+            mov8 $zero $yielding
+            corestore $context_a
+            #b_resumed_2
+            hlt64
+        ");
+        assert_eq!(run.value("trace"), 1212);
+    }
+
+    #[test]
+    fn profiling_groups_instructions_by_region() {
+        let run = run_with(
+            "
+            set8 $counter 0
+            set8 $one 1
+            profr 1
+            add8 $counter $one $counter
+            add8 $counter $one $counter
+            add8 $counter $one $counter
+            profr 2
+            add8 $counter $one $counter
+            profr 1
+            hlt64
+            ",
+            |state| state.enable_profiling(),
+        );
+        // Every region counts the profr that entered it
+        assert_eq!(run.state.profile, Some(BTreeMap::from([(1, 6), (2, 2)])));
+        let report = run.state.profile_report().unwrap();
+        assert!(report.contains("Region 1: 6 instructions (75.0%)"), "{}", report);
+        assert!(report.contains("Region 2: 2 instructions (25.0%)"), "{}", report);
+    }
+
+    #[test]
+    fn repeat_walks_arrays() {
+        let source = declare_bytes("a", &[1, 2, 3, 4]) + &declare_bytes("b", &[10, 20, 30, 40]) + "
+            set64 $count 4
+            rep $count
+            add8 $a $b $a
+            hlt64
+        ";
+        let run = run(&source);
+        assert_eq!(run.bytes("a", 4), [11, 22, 33, 44]);
+        assert_eq!(run.bytes("b", 4), [10, 20, 30, 40]);
+    }
+
+    /// Records the opcode and address of every instruction the processor executes
+    fn record_instructions(state: &mut TransientState<65536>) -> Rc<RefCell<Vec<(u8, usize)>>> {
+        let executed = Rc::new(RefCell::new(vec![]));
+        let recorder = executed.clone();
+        state.set_hook(move |instruction, address| recorder.borrow_mut().push((instruction[0], address)));
+        executed
+    }
+
+    #[test]
+    fn repeat_hooks_and_counts_every_iteration() {
+        let source = declare_bytes("a", &[0, 0, 0, 0]) + "
+            set64 $count 3
+            rep $count
+            not8 $a $a
+            hlt64
+        ";
+        let mut executed = Rc::default();
+        let run = run_with(&source, |state| {
+            executed = record_instructions(state);
+            state.enable_profiling();
+        });
+        assert_eq!(run.bytes("a", 4), [255, 255, 255, 0]);
+        // rep is 5 bytes long and not 14
+        assert_eq!(*executed.borrow(), [(REPEAT, 0), (NOT, 5), (NOT, 5), (NOT, 5), (HLT, 19)]);
+        assert_eq!(run.state.profile, Some(BTreeMap::from([(0, 5)])));
+        assert_eq!(run.state.instructions_executed, 5);
+    }
+
+    #[test]
+    fn repeat_rejects_jumps_and_halts() {
+        for repeated in ["jmp64 #end", "call #end", "ret", "hlt64"] {
+            let run = try_run_with_input(&format!("set64 $count 2\nrep $count\n{}\n#end\nhlt64", repeated), &[]);
+            assert!(matches!(run.result, Err(TransientError::InvalidOperand(_))), "{}", repeated);
+        }
+    }
+
+    #[test]
+    fn delay_slot_is_hooked_and_counted() {
+        let source = "
+            set64 $a 0
+            jmpd64 #end
+            not64 $a $a
+            not64 $a $a
+            #end
+            hlt64
+        ";
+        let mut executed = Rc::default();
+        let run = run_with(source, |state| {
+            executed = record_instructions(state);
+            state.enable_profiling();
+        });
+        assert_eq!(run.value("a"), u64::MAX);
+        assert_eq!(*executed.borrow(), [(JMP, 0), (NOT, 14), (HLT, 42)]);
+        assert_eq!(run.state.profile, Some(BTreeMap::from([(0, 3)])));
+        assert_eq!(run.state.instructions_executed, 3);
+    }
+
+    #[test]
+    fn delay_slot_rejects_jumps() {
+        let run = try_run_with_input("jmpd64 #end\njmp64 #end\n#end\nhlt64", &[]);
+        assert!(matches!(run.result, Err(TransientError::InvalidOperand(_))));
+    }
+
+    #[test]
+    fn delay_slot_must_be_inside_the_code_region() {
+        // Only the jump itself is code
+        let source = "set64 $a 0\njmpd64 #end\nnot64 $a $a\n#end\nhlt64";
+        let run = try_run_with(source, &[], |state| state.enable_sandbox(0, 14, 0, 65536));
+        assert!(matches!(run.result, Err(TransientError::SandboxViolation { addr: 14 })));
+        assert_eq!(run.value("a"), 0);
+    }
+
+    #[test]
+    fn sandbox_rejects_accesses_outside_of_the_data_region() {
+        let source = "
+            set64 $inside 1
+            set64 $outside 2
+            mov64 $outside $inside
+            hlt64
+        ";
+        let sandbox = |state: &mut TransientState<65536>| {
+            // Code runs up to the first variable, only $inside is data
+            let inside = state.symbols.iter().find(|(x, ..)| x == "inside").unwrap().1;
+            state.enable_sandbox(0, inside, inside, 8);
+        };
+        let run = try_run_with(source, &[], sandbox);
+        assert!(matches!(run.result, Err(TransientError::SandboxViolation { addr }) if addr == run.address("outside")));
+        assert_eq!(run.value("inside"), 1);
+    }
+
+    fn predicated_move(condition: u64) -> u64 {
+        run(&format!("
+            set64 $condition {}
+            set64 $value 42
+            set64 $result 7
+            setpred $condition
+            ?mov64 $value $result
+            clrpred
+            hlt64
+        ", condition)).value("result")
+    }
+
+    #[test]
+    fn predicated_mov_executes_if_the_condition_is_set() {
+        assert_eq!(predicated_move(1), 42);
+        assert_eq!(predicated_move(0x180), 42);
+    }
+
+    #[test]
+    fn predicated_mov_is_skipped_if_the_condition_is_clear() {
+        assert_eq!(predicated_move(0), 7);
+        // Only the lowest byte is loaded into the condition register
+        assert_eq!(predicated_move(0x100), 7);
+    }
+
+    #[test]
+    fn clrpred_reenables_predicated_operations() {
+        let run = run("
+            set64 $zero 0
+            set64 $value 42
+            set64 $result 7
+            setpred $zero
+            clrpred
+            ?mov64 $value $result
+            hlt64
+        ");
+        assert_eq!(run.value("result"), 42);
+        assert_eq!(run.state.condition_register, 1);
+    }
+
+    #[test]
+    fn addmod_does_not_overflow_in_between() {
+        let run = run("
+            set64 $max 18446744073709551615
+            set64 $one 1
+            set64 $three 3
+            set64 $result 0
+            set8 $a 200
+            set8 $b 100
+            set8 $seven 7
+            set8 $small 0
+            addmod64 $max $one $three $result
+            addmod8 $a $b $seven $small
+            hlt64
+        ");
+        assert_eq!(run.value("result"), ((u64::MAX as u128 + 1) % 3) as u64);
+        assert_eq!(run.value("small"), 300 % 7);
+    }
+
+    #[test]
+    fn mulmod_does_not_overflow_in_between() {
+        let run = run("
+            set64 $max 18446744073709551615
+            set64 $modulus 18446744073709551557
+            set64 $result 0
+            mulmod64 $max $max $modulus $result
+            hlt64
+        ");
+        assert_eq!(run.value("result"), (u64::MAX as u128 * u64::MAX as u128 % 18446744073709551557) as u64);
+    }
+
+    #[test]
+    fn addmod_rejects_a_zero_modulus() {
+        let run = try_run_with_input("
+            set64 $one 1
+            set64 $zero 0
+            set64 $result 0
+            addmod64 $one $one $zero $result
+            hlt64
+        ", &[]);
+        assert!(matches!(run.result, Err(TransientError::DivisionByZero)));
+    }
+
+    #[test]
+    fn modpow_raises_to_a_power() {
+        let run = run("
+            set16 $two 2
+            set16 $ten 10
+            set16 $thousand 1000
+            set16 $result 0
+            modpow16 $two $ten $thousand $result
+            hlt64
+        ");
+        assert_eq!(run.value("result"), 24);
+    }
+
+    #[test]
+    fn modpow_round_trips_tiny_rsa() {
+        // n = 61 * 53, e * d = 1 mod lcm(60, 52)
+        let run = run("
+            set64 $message 65
+            set64 $public 17
+            set64 $private 2753
+            set64 $modulus 3233
+            set64 $cipher 0
+            set64 $plain 0
+            modpow64 $message $public $modulus $cipher
+            modpow64 $cipher $private $modulus $plain
+            hlt64
+        ");
+        assert_eq!(run.value("cipher"), 2790);
+        assert_eq!(run.value("plain"), 65);
+    }
+
+    #[test]
+    fn modpow_rejects_a_modulus_below_two() {
+        let run = try_run_with_input("
+            set64 $two 2
+            set64 $one 1
+            set64 $result 0
+            modpow64 $two $two $one $result
+            hlt64
+        ", &[]);
+        assert!(matches!(run.result, Err(TransientError::InvalidOperand(_))));
+    }
+
+    #[test]
+    fn invmod_inverts_coprime_values() {
+        let run = run("
+            set16 $value 3
+            set16 $modulus 11
+            set16 $inverse 0
+            set16 $exists 0
+            invmod16 $value $modulus $inverse $exists
+            hlt64
+        ");
+        assert_eq!(run.value("inverse"), 4);
+        assert_eq!(run.value("exists"), 1);
+    }
+
+    #[test]
+    fn invmod_reports_non_coprime_values() {
+        let run = run("
+            set16 $value 6
+            set16 $modulus 9
+            set16 $inverse 5
+            set16 $exists 1
+            invmod16 $value $modulus $inverse $exists
+            hlt64
+        ");
+        assert_eq!(run.value("inverse"), 0);
+        assert_eq!(run.value("exists"), 0);
+    }
+
+    #[test]
+    fn xorshift64_reproduces_the_seeded_sequence() {
+        let run = run("
+            set64 $state 1
+            set64 $first 0
+            set64 $second 0
+            set64 $third 0
+            set64 $fourth 0
+            xorsh64 $state $first
+            xorsh64 $state $second
+            xorsh64 $state $third
+            xorsh64 $state $fourth
+            hlt64
+        ");
+        assert_eq!(run.value("first"), 1082269761);
+        assert_eq!(run.value("second"), 1152992998833853505);
+        assert_eq!(run.value("third"), 11177516664432764457);
+        assert_eq!(run.value("fourth"), 17678023832001937445);
+        assert_eq!(run.value("state"), 17678023832001937445);
+    }
+
+    #[test]
+    fn xorshift64_rejects_a_zero_state() {
+        let run = try_run_with_input("
+            set64 $state 0
+            set64 $result 0
+            xorsh64 $state $result
+            hlt64
+        ", &[]);
+        assert!(matches!(run.result, Err(TransientError::InvalidOperand(_))));
+    }
+
+    #[test]
+    fn chacha20_matches_rfc_8439() {
+        // Test vector for the ChaCha20 block function, RFC 8439 section 2.3.2
+        let words: [u32; 16] = [
+            0x61707865, 0x3320646e, 0x79622d32, 0x6b206574, 0x03020100, 0x07060504, 0x0b0a0908, 0x0f0e0d0c,
+            0x13121110, 0x17161514, 0x1b1a1918, 0x1f1e1d1c, 0x00000001, 0x09000000, 0x4a000000, 0x00000000,
+        ];
+        let state: Vec<u8> = words.iter().flat_map(|x| x.to_le_bytes()).collect();
+        let source = declare_bytes("state", &state) + &declare_bytes("keystream", &[0; 64]) + "
+            chacha20 $state $keystream
+            hlt64
+        ";
+        let expected = [
+            0x10, 0xf1, 0xe7, 0xe4, 0xd1, 0x3b, 0x59, 0x15, 0x50, 0x0f, 0xdd, 0x1f, 0xa3, 0x20, 0x71, 0xc4,
+            0xc7, 0xd1, 0xf4, 0xc7, 0x33, 0xc0, 0x68, 0x03, 0x04, 0x22, 0xaa, 0x9a, 0xc3, 0xd4, 0x6c, 0x4e,
+            0xd2, 0x82, 0x64, 0x46, 0x07, 0x9f, 0xaa, 0x09, 0x14, 0xc2, 0xd7, 0x05, 0xd9, 0x8b, 0x02, 0xa2,
+            0xb5, 0x12, 0x9c, 0xd1, 0xde, 0x16, 0x4e, 0xb9, 0xcb, 0xd0, 0x83, 0xe8, 0xa2, 0x50, 0x3c, 0x4e,
+        ];
+        let run = run(&source);
+        assert_eq!(run.bytes("keystream", 64), expected);
+        assert_eq!(run.bytes("state", 64), state);
+    }
+
+    #[test]
+    fn sha256_compresses_abc() {
+        let initial: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+        let state: Vec<u8> = initial.iter().flat_map(|x| x.to_be_bytes()).collect();
+        // "abc" padded to a single block, ending in its length in bits
+        let mut block = [0u8; 64];
+        block[..4].copy_from_slice(b"abc\x80");
+        block[63] = 24;
+        let source = declare_bytes("state", &state) + &declare_bytes("block", &block) + "
+            sha256 $state $block
+            hlt64
+        ";
+        let expected = [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+            0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+        ];
+        assert_eq!(run(&source).bytes("state", 32), expected);
+    }
+
+    #[test]
+    fn popstack_discards_the_top_of_the_stack() {
+        let run = run("
+            set32 $first 1
+            set32 $second 2
+            set64 $eight 8
+            push32 $first
+            push32 $second
+            popstk $eight
+            hlt64
+        ");
+        assert_eq!(run.state.stack_pointer, 65536);
+    }
+
+    #[test]
+    fn popstack_rejects_discarding_more_than_the_stack_holds() {
+        let run = try_run_with_input("
+            set32 $first 1
+            set64 $eight 8
+            push32 $first
+            popstk $eight
+            hlt64
+        ", &[]);
+        assert!(matches!(run.result, Err(TransientError::StackUnderflow)));
+    }
+
+    #[test]
+    fn peek_does_not_disturb_the_stack() {
+        let run = run("
+            set32 $value 1234
+            set32 $top 0
+            set32 $popped 0
+            set32 $same 0
+            push32 $value
+            peek32 $top
+            equ32 $top $value $same
+            pop32 $popped
+            hlt64
+        ");
+        assert_eq!(run.value("top"), 1234);
+        assert_eq!(run.value("same"), 1);
+        assert_eq!(run.value("popped"), 1234);
+        assert_eq!(run.state.stack_pointer, 65536);
+    }
+
+    #[test]
+    fn peek_rejects_an_empty_stack() {
+        let run = try_run_with_input("
+            set32 $top 0
+            peek32 $top
+            hlt64
+        ", &[]);
+        assert!(matches!(run.result, Err(TransientError::StackUnderflow)));
+    }
+
+    #[test]
+    fn pushpc_and_poppc_switch_between_coroutines() {
+        // A pushpc in the delay slot of a jump saves the continuation after it. #switch swaps the
+        // two continuations on the stack and resumes the other coroutine
+        let run = run("
+            set64 $trace 0
+            set64 $ten 10
+            set64 $a 1
+            set64 $b 2
+            set32 $mine 0
+            set32 $theirs 0
+            mul64 $trace $ten $trace
+            add64 $trace $a $trace
+            jmpd64 #worker
+            pushpc
+            mul64 $trace $ten $trace
+            add64 $trace $a $trace
+            jmpd64 #switch
+            pushpc
+            mul64 $trace $ten $trace
+            add64 $trace $a $trace
+            hlt64
+            #worker
+            mul64 $trace $ten $trace
+            add64 $trace $b $trace
+            jmpd64 #switch
+            pushpc
+            mul64 $trace $ten $trace
+            add64 $trace $b $trace
+            jmpd64 #switch
+            pushpc
+            hlt64
+            #switch
+            pop32 $mine
+            pop32 $theirs
+            push32 $mine
+            push32 $theirs
+            poppc
+        ");
+        assert_eq!(run.value("trace"), 12121);
+        // The worker is still suspended
+        assert_eq!(run.state.stack_pointer, 65536 - 4);
+    }
+
+    fn vector_compare(operation: &str, a: &[u8; 4], b: &[u8; 4]) -> Vec<u8> {
+        let source = declare_bytes("a", a) + &declare_bytes("b", b) + &declare_bytes("result", &[9; 4]) + &format!("
+            set64 $count 4
+            {} $a $b $count $result
+            hlt64
+        ", operation);
+        run(&source).bytes("result", 4).to_vec()
+    }
+
+    #[test]
+    fn vcmpeq8_compares_bytes() {
+        assert_eq!(vector_compare("vcmpeq8", &[1, 2, 3, 4], &[1, 2, 3, 4]), [1, 1, 1, 1]);
+        assert_eq!(vector_compare("vcmpeq8", &[1, 2, 3, 4], &[1, 0, 3, 0]), [1, 0, 1, 0]);
+        assert_eq!(vector_compare("vcmpeq8", &[1, 2, 3, 4], &[5, 6, 7, 8]), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn vcmpgt8_compares_bytes() {
+        assert_eq!(vector_compare("vcmpgt8", &[1, 2, 3, 4], &[1, 2, 3, 4]), [0, 0, 0, 0]);
+        assert_eq!(vector_compare("vcmpgt8", &[1, 2, 3, 255], &[0, 2, 4, 254]), [1, 0, 0, 1]);
+        assert_eq!(vector_compare("vcmpgt8", &[5, 6, 7, 8], &[1, 2, 3, 4]), [1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn dotprod_sums_the_products() {
+        let source = declare_bytes("a", &[1, 2, 3, 4, 255, 255]) + &declare_bytes("b", &[5, 6, 7, 8, 255, 255]) + "
+            set64 $short 4
+            set64 $long 6
+            set64 $small 0
+            set64 $large 0
+            dotprod $short $a $b $small
+            dotprod $long $a $b $large
+            hlt64
+        ";
+        let run = run(&source);
+        assert_eq!(run.value("small"), 70);
+        assert_eq!(run.value("large"), 70 + 2 * 255 * 255);
+    }
+
+    #[test]
+    fn tlookup_substitutes_through_the_aes_sbox() {
+        const SBOX: [u8; 256] = [
+            0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+            0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+            0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+            0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+            0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+            0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+            0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+            0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+            0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+            0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+            0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+            0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+            0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+            0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+            0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+            0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+        ];
+        let source = declare_bytes("sbox", &SBOX) + "
+            set64 $zero 0
+            set64 $last 255
+            set64 $example 83
+            set64 $pair 1
+            set8 $first 0
+            set8 $final 0
+            set8 $substituted 0
+            set16 $wide 0
+            tlookup $zero $sbox 1 $first
+            tlookup $last $sbox 1 $final
+            tlookup $example $sbox 1 $substituted
+            tlookup $pair $sbox 2 $wide
+            hlt64
+        ";
+        let run = run(&source);
+        assert_eq!(run.value("first"), 0x63);
+        assert_eq!(run.value("final"), 0x16);
+        // FIPS-197 section 5.1.1: S-box(0x53) = 0xED
+        assert_eq!(run.value("substituted"), 0xED);
+        assert_eq!(run.value("wide"), 0x777B);
+    }
+
+    #[test]
+    fn tlookup_rejects_indices_outside_of_memory() {
+        let run = try_run_with_input("
+            set64 $index 18446744073709551615
+            set8 $table 0
+            set8 $result 0
+            tlookup $index $table 2 $result
+            hlt64
+        ", &[]);
+        assert!(matches!(run.result, Err(TransientError::InvalidOperand(_))));
+    }
+
+    #[test]
+    fn setout_redirects_following_output() {
+        let log = SharedBuffer::default();
+        let device = log.clone();
+        let run = run_with(
+            "
+            set8 $first 1
+            set8 $second 2
+            set8 $third 3
+            set64 $console 0
+            set64 $log 7
+            puti8 $first
+            setout $log
+            puti8 $second
+            setout $console
+            puti8 $third
+            hlt64
+            ",
+            |state| state.register_output_device(7, Box::new(device)),
+        );
+        assert_eq!(run.console.contents(), "13");
+        assert_eq!(log.contents(), "2");
+    }
+
+    #[test]
+    fn setin_redirects_following_input() {
+        let run = run_with(
+            "
+            set64 $file 3
+            set8 $value 0
+            setin $file
+            getc8 $value
+            hlt64
+            ",
+            |state| state.register_input_device(3, Box::new(&b"A"[..])),
+        );
+        assert_eq!(run.value("value"), b'A' as u64);
+    }
+
+    #[test]
+    fn setout_rejects_unknown_devices() {
+        let run = try_run_with_input("
+            set64 $device 9
+            setout $device
+            hlt64
+        ", &[]);
+        assert!(matches!(run.result, Err(TransientError::UnknownDevice(9))));
+    }
+
+    fn bounded_loop(count: u64) -> TestRun {
+        run(&format!("
+            set64 $count {}
+            set64 $three 3
+            set64 $outer 0
+            set64 $inner 0
+            set64 $one 1
+            set64 $done 0
+            bloopn $count #outer
+            add64 $done $one $done
+            hlt64
+            #outer
+            add64 $outer $one $outer
+            bloopn $three #inner
+            lend
+            #inner
+            add64 $inner $one $inner
+            lend
+        ", count))
+    }
+
+    #[test]
+    fn bloopn_runs_the_body_count_times() {
+        let run = bounded_loop(5);
+        assert_eq!(run.value("outer"), 5);
+        assert_eq!(run.value("inner"), 15);
+        assert_eq!(run.value("done"), 1);
+        assert!(run.state.loops.is_empty());
+    }
+
+    #[test]
+    fn bloopn_skips_the_body_for_a_zero_count() {
+        let run = bounded_loop(0);
+        assert_eq!(run.value("outer"), 0);
+        assert_eq!(run.value("done"), 1);
+    }
+
+    fn reverse_find(haystack: &[u8], needle: u8) -> u64 {
+        let source = declare_bytes("haystack", haystack) + &format!("
+            set64 $length {}
+            set8 $needle {}
+            set64 $offset 0
+            rfind $haystack $length $needle $offset
+            hlt64
+        ", haystack.len(), needle);
+        run(&source).value("offset")
+    }
+
+    #[test]
+    fn rfind_finds_the_last_occurrence() {
+        assert_eq!(reverse_find(b"a/b/c/", b'/'), 5);
+        assert_eq!(reverse_find(b"a/b/c", b'/'), 3);
+    }
+
+    #[test]
+    fn rfind_returns_the_length_if_nothing_matches() {
+        assert_eq!(reverse_find(b"abc", b'/'), 3);
+    }
+
+    #[test]
+    fn splitb_writes_bytes_in_big_endian_order() {
+        let source = declare_bytes("bytes", &[0; 4]) + "
+            set32 $value 305419896
+            splitb32 $value $bytes
+            hlt64
+        ";
+        let run = run(&source);
+        assert_eq!(run.value("bytes"), 0x12);
+        assert_eq!(run.value("bytes_1"), 0x34);
+        assert_eq!(run.value("bytes_2"), 0x56);
+        assert_eq!(run.value("bytes_3"), 0x78);
+    }
+
+    #[test]
+    fn joinb_recovers_the_value_split_by_splitb() {
+        let source = declare_bytes("bytes", &[0; 8]) + "
+            set64 $value 81985529216486895
+            set64 $joined 0
+            set16 $short 0
+            splitb64 $value $bytes
+            joinb64 $bytes $joined
+            joinb16 $bytes $short
+            hlt64
+        ";
+        let run = run(&source);
+        assert_eq!(run.bytes("bytes", 8), [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
+        assert_eq!(run.value("joined"), 0x0123456789ABCDEF);
+        assert_eq!(run.value("short"), 0x0123);
+    }
+
+    #[test]
+    fn interleave_alternates_the_channels() {
+        let source = declare_bytes("left", &[0xA0, 0xA1, 0xA2]) + &declare_bytes("right", &[0xB0, 0xB1, 0xB2]) + &declare_bytes("mixed", &[0; 6]) + "
+            set64 $count 3
+            interleave $left $right $count $mixed
+            hlt64
+        ";
+        assert_eq!(run(&source).bytes("mixed", 6), [0xA0, 0xB0, 0xA1, 0xB1, 0xA2, 0xB2]);
+    }
+
+    #[test]
+    fn deinterleave_separates_the_channels() {
+        let source = declare_bytes("mixed", &[0xA0, 0xB0, 0xA1, 0xB1, 0xA2, 0xB2]) + &declare_bytes("left", &[0; 3]) + &declare_bytes("right", &[0; 3]) + "
+            set64 $count 3
+            deinterleave $mixed $count $left $right
+            hlt64
+        ";
+        let run = run(&source);
+        assert_eq!(run.bytes("left", 3), [0xA0, 0xA1, 0xA2]);
+        assert_eq!(run.bytes("right", 3), [0xB0, 0xB1, 0xB2]);
+    }
+
+    #[test]
+    fn interleave_rejects_overflowing_counts() {
+        let run = try_run_with_input("
+            set8 $left 0
+            set8 $right 0
+            set8 $mixed 0
+            set64 $count 9223372036854775809
+            interleave $left $right $count $mixed
+            hlt64
+        ", &[]);
+        assert!(matches!(run.result, Err(TransientError::OutOfBoundsAccess { .. })));
+    }
+
+    /// Run-length encodes `data` and decodes it again, returning both results
+    fn run_length_round_trip(data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let capacity = data.len() * 2;
+        let source = declare_bytes("data", data) + &declare_bytes("encoded", &vec![0; capacity]) + &declare_bytes("decoded", &vec![0; capacity]) + &format!("
+            set64 $length {}
+            set64 $capacity {}
+            set64 $encoded_length 0
+            set64 $decoded_length 0
+            rleenc $data $length $encoded $capacity $encoded_length
+            rledec $encoded $encoded_length $decoded $capacity $decoded_length
+            hlt64
+        ", data.len(), capacity);
+        let run = run(&source);
+        let encoded = run.bytes("encoded", run.value("encoded_length") as usize).to_vec();
+        let decoded = run.bytes("decoded", run.value("decoded_length") as usize).to_vec();
+        (encoded, decoded)
+    }
+
+    #[test]
+    fn rle_splits_long_runs() {
+        let data = [7; 300];
+        let (encoded, decoded) = run_length_round_trip(&data);
+        assert_eq!(encoded, [255, 7, 45, 7]);
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn rle_encodes_data_without_runs() {
+        let (encoded, decoded) = run_length_round_trip(&[1, 2, 3]);
+        assert_eq!(encoded, [1, 1, 1, 2, 1, 3]);
+        assert_eq!(decoded, [1, 2, 3]);
+    }
+
+    #[test]
+    fn rle_encodes_alternating_bytes() {
+        let data = [0xAA, 0x55, 0xAA, 0x55, 0x55];
+        let (encoded, decoded) = run_length_round_trip(&data);
+        assert_eq!(encoded, [1, 0xAA, 1, 0x55, 1, 0xAA, 2, 0x55]);
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn rleenc_rejects_a_small_destination() {
+        let source = declare_bytes("data", &[1, 2]) + "
+            set64 $length 2
+            set64 $capacity 3
+            set64 $encoded 0
+            set64 $encoded_length 0
+            rleenc $data $length $encoded $capacity $encoded_length
+            hlt64
+        ";
+        assert!(matches!(try_run_with_input(&source, &[]).result, Err(TransientError::InvalidOperand(_))));
+    }
+
+    #[test]
+    fn delta_encoding_round_trips() {
+        let data = [10, 12, 11, 11, 250, 3];
+        let source = declare_bytes("data", &data) + &declare_bytes("deltas", &[0; 6]) + &declare_bytes("restored", &[0; 6]) + "
+            set64 $length 6
+            deltaenc $data $length $deltas
+            deltadec $deltas $length $restored
+            hlt64
+        ";
+        let run = run(&source);
+        assert_eq!(run.bytes("deltas", 6), [10, 2, 255, 0, 239, 9]);
+        assert_eq!(run.bytes("restored", 6), data);
+    }
+
+    /// Packs `values` into nibbles and unpacks them again, returning both results
+    fn nibble_round_trip(values: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let packed_length = values.len().div_ceil(2);
+        let source = declare_bytes("values", values) + &declare_bytes("packed", &vec![0xFF; packed_length]) + &declare_bytes("unpacked", &vec![0xFF; values.len()]) + &format!("
+            set64 $count {}
+            nibpk $values $count $packed
+            nibupk $packed $count $unpacked
+            hlt64
+        ", values.len());
+        let run = run(&source);
+        (run.bytes("packed", packed_length).to_vec(), run.bytes("unpacked", values.len()).to_vec())
+    }
+
+    #[test]
+    fn nibbles_pack_an_even_count() {
+        let (packed, unpacked) = nibble_round_trip(&[1, 2, 0xA, 0xF]);
+        assert_eq!(packed, [0x12, 0xAF]);
+        assert_eq!(unpacked, [1, 2, 0xA, 0xF]);
+    }
+
+    #[test]
+    fn nibbles_pack_an_odd_count() {
+        let (packed, unpacked) = nibble_round_trip(&[1, 2, 3]);
+        assert_eq!(packed, [0x12, 0x30]);
+        assert_eq!(unpacked, [1, 2, 3]);
+    }
+
+    #[test]
+    fn fibstep_reaches_the_94th_fibonacci_number() {
+        // Counting from 0, 1 the 94th Fibonacci number is the largest one that fits in 64 bits.
+        // The next one wraps around
+        let run = run("
+            set64 $a 0
+            set64 $b 1
+            set64 $steps 93
+            bloopn $steps #step
+            hlt64
+            #step
+            fibstep $a $b
+            lend
+        ");
+        assert_eq!(run.value("a"), 12200160415121876738);
+        assert_eq!(run.value("b"), (19740274219868223167u128 % (1 << 64)) as u64);
+    }
+
+    fn binary_search(target: u8) -> u64 {
+        let source = declare_bytes("sorted", &[2, 3, 5, 7, 11, 13]) + &format!("
+            set64 $count 6
+            set8 $target {}
+            set64 $index 0
+            bsearch $sorted $count $target $index
+            hlt64
+        ", target);
+        run(&source).value("index")
+    }
+
+    #[test]
+    fn bsearch_finds_present_values() {
+        assert_eq!(binary_search(2), 0);
+        assert_eq!(binary_search(7), 3);
+        assert_eq!(binary_search(13), 5);
+    }
+
+    #[test]
+    fn bsearch_returns_the_complement_of_the_insertion_point() {
+        assert_eq!(binary_search(0), !0);
+        assert_eq!(binary_search(6), !3);
+        assert_eq!(binary_search(200), !6);
+    }
+
+    fn quicksort(data: &[u8], element_size: usize, descending: bool) -> Vec<u8> {
+        let source = declare_bytes("data", data) + &format!("
+            set64 $count {}
+            set64 $element_size {}
+            set64 $descending {}
+            qsort $data $count $element_size $descending
+            hlt64
+        ", data.len() / element_size, element_size, descending as u8);
+        run(&source).bytes("data", data.len()).to_vec()
+    }
+
+    #[test]
+    fn qsort_sorts_bytes() {
+        assert_eq!(quicksort(&[5, 1, 4, 1, 3], 1, false), [1, 1, 3, 4, 5]);
+        assert_eq!(quicksort(&[5, 1, 4, 1, 3], 1, true), [5, 4, 3, 1, 1]);
+    }
+
+    #[test]
+    fn qsort_sorts_16_bit_elements() {
+        let data = [0x01, 0x00, 0x00, 0xFF, 0x00, 0x01];
+        assert_eq!(quicksort(&data, 2, false), [0x00, 0x01, 0x00, 0xFF, 0x01, 0x00]);
+        assert_eq!(quicksort(&data, 2, true), [0x01, 0x00, 0x00, 0xFF, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn qsort_sorts_32_bit_elements() {
+        let data = [0, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 2];
+        assert_eq!(quicksort(&data, 4, false), [0, 0, 0, 2, 0, 0, 1, 0, 9, 0, 0, 0]);
+        assert_eq!(quicksort(&data, 4, true), [9, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 2]);
+    }
+
+    /// Runs UNIQSORT over `data` and returns the unique bytes it kept
+    fn unique_sort(data: &[u8]) -> Vec<u8> {
+        let source = declare_bytes("data", data) + &format!("
+            set64 $count {}
+            set64 $unique 0
+            uniqsort $data $count $unique
+            hlt64
+        ", data.len());
+        let run = run(&source);
+        run.bytes("data", run.value("unique") as usize).to_vec()
+    }
+
+    #[test]
+    fn uniqsort_removes_duplicates() {
+        assert_eq!(unique_sort(&[4, 4, 4, 4]), [4]);
+        assert_eq!(unique_sort(&[3, 1, 2]), [1, 2, 3]);
+        assert_eq!(unique_sort(&[5, 1, 5, 3, 1, 9]), [1, 3, 5, 9]);
+    }
+
+    fn percentile(percentile: u64) -> TestRun {
+        let source = declare_bytes("data", &[35, 50, 15, 40, 20]) + &format!("
+            set64 $count 5
+            set64 $percentile {}
+            set64 $value 0
+            pctile $data $count $percentile $value
+            hlt64
+        ", percentile);
+        run(&source)
+    }
+
+    #[test]
+    fn pctile_uses_the_nearest_rank() {
+        assert_eq!(percentile(0).value("value"), 15);
+        assert_eq!(percentile(50).value("value"), 35);
+        assert_eq!(percentile(75).value("value"), 40);
+        assert_eq!(percentile(100).value("value"), 50);
+    }
+
+    #[test]
+    fn pctile_leaves_the_array_unsorted() {
+        assert_eq!(percentile(50).bytes("data", 5), [35, 50, 15, 40, 20]);
+    }
+
+    #[test]
+    fn histo_counts_every_byte_value() {
+        let source = declare_bytes("counts", &[0xFF; 512]) + "
+            setstr $text \"hello world\"
+            set64 $length 11
+            histo $text $length $counts
+            hlt64
+        ";
+        let run = run(&source);
+        let counts = run.bytes("counts", 512);
+        let count = |byte: u8| u16::from_be_bytes([counts[byte as usize * 2], counts[byte as usize * 2 + 1]]);
+        assert_eq!(count(b'l'), 3);
+        assert_eq!(count(b'o'), 2);
+        assert_eq!(count(b'h'), 1);
+        assert_eq!(count(b' '), 1);
+        assert_eq!(count(b'z'), 0);
+        assert_eq!((0..=255).map(count).sum::<u16>(), 11);
+    }
+
+    #[test]
+    fn wsample_follows_the_weights() {
+        let source = declare_bytes("weights", &[1, 3, 0, 4]) + "
+            set64 $count 4
+            set64 $state 88172645463325252
+            set64 $samples 8000
+            set64 $index 0
+            set64 $hit 0
+            set64 $first 0
+            set64 $second 1
+            set64 $third 2
+            set64 $fourth 3
+            set64 $first_count 0
+            set64 $second_count 0
+            set64 $third_count 0
+            set64 $fourth_count 0
+            bloopn $samples #sample
+            hlt64
+            #sample
+            wsample $weights $count $state $index
+            equ64 $index $first $hit
+            add64 $first_count $hit $first_count
+            equ64 $index $second $hit
+            add64 $second_count $hit $second_count
+            equ64 $index $third $hit
+            add64 $third_count $hit $third_count
+            equ64 $index $fourth $hit
+            add64 $fourth_count $hit $fourth_count
+            lend
+        ";
+        let run = run(&source);
+        // Every index is expected 1000 times per unit of weight, give or take a few percent
+        let expected = [("first_count", 1000), ("second_count", 3000), ("fourth_count", 4000)];
+        for (name, count) in expected {
+            assert!(run.value(name).abs_diff(count) < 200, "{} was {}", name, run.value(name));
+        }
+        assert_eq!(run.value("third_count"), 0);
+    }
+
+    /// Squares an 8x8 bit matrix with BITMATMUL
+    fn square_bit_matrix(rows: [u64; 8]) -> Vec<u64> {
+        let mut source: String = rows.iter().enumerate().map(|(i, row)| format!("set64 $matrix_{} {}\n", i, row)).collect();
+        source += &(0..8).map(|i| format!("set64 $product_{} 0\n", i)).collect::<String>();
+        source += "
+            set64 $eight 8
+            bitmatmul $eight $eight $eight $matrix_0 $matrix_0 $product_0
+            hlt64
+        ";
+        let run = run(&source);
+        (0..8).map(|i| run.value(&format!("product_{}", i))).collect()
+    }
+
+    #[test]
+    fn bitmatmul_squares_an_adjacency_matrix() {
+        // A cycle through 8 nodes, squared, connects every node to the one two steps ahead
+        let cycle = core::array::from_fn(|i| 1 << ((i + 1) % 8));
+        let two_steps: Vec<u64> = (0..8).map(|i| 1 << ((i + 2) % 8)).collect();
+        assert_eq!(square_bit_matrix(cycle), two_steps);
+    }
+
+    #[test]
+    fn bitmatmul_adds_with_xor() {
+        // Both paths of length 2 from i to i + 3 cancel out over GF(2)
+        let skips = core::array::from_fn(|i| 1 << ((i + 1) % 8) | 1 << ((i + 2) % 8));
+        let expected: Vec<u64> = (0..8).map(|i| 1 << ((i + 2) % 8) | 1 << ((i + 4) % 8)).collect();
+        assert_eq!(square_bit_matrix(skips), expected);
+    }
+
+    fn count_bits(data: &[u8]) -> u64 {
+        let source = declare_bytes("data", data) + &format!("
+            set64 $length {}
+            set32 $bits 0
+            popcnta $data $length $bits
+            hlt64
+        ", data.len());
+        run(&source).value("bits")
+    }
+
+    #[test]
+    fn popcnta_counts_bits_across_the_array() {
+        assert_eq!(count_bits(&[0xFF; 16]), 128);
+        // Chunks of 8 bytes followed by a remainder
+        assert_eq!(count_bits(&[1, 3, 7, 15, 31, 63, 127, 255, 0x80, 0x81, 0]), 36 + 3);
+        assert_eq!(count_bits(&[0; 5]), 0);
+    }
+
+    #[test]
+    fn dither_turns_mid_gray_into_a_checkerboard() {
+        let source = declare_bytes("image", &[128; 32]) + &declare_bytes("dithered", &[7; 32]) + "
+            set64 $width 8
+            set64 $height 4
+            dither $image $width $height $dithered
+            hlt64
+        ";
+        let checkerboard: Vec<u8> = (0..32).map(|i| if (i % 8 + i / 8) % 2 == 0 { 255 } else { 0 }).collect();
+        assert_eq!(run(&source).bytes("dithered", 32), checkerboard);
+    }
+
+    const COUNT_TO_THREE: &str = "
+        set64 $counter 0
+        set64 $one 1
+        add64 $counter $one $counter
+        add64 $counter $one $counter
+        add64 $counter $one $counter
+        hlt64
+    ";
+
+    #[test]
+    fn run_pauses_at_a_breakpoint_and_resumes() {
+        // Every add is 14 bytes long, so the third one is at 28
+        let mut run = run_with(COUNT_TO_THREE, |state| state.set_breakpoint(28));
+        assert!(run.state.is_paused());
+        assert_eq!(run.state.program_counter, 28);
+        assert_eq!(run.value("counter"), 2);
+        run.state.run(0).unwrap();
+        assert_eq!(run.state.mode, TransientMode::HALTED(None));
+        assert_eq!(run.value("counter"), 3);
+    }
+
+    #[test]
+    fn restore_returns_to_a_snapshot() {
+        let mut run = run_with(COUNT_TO_THREE, |state| state.set_breakpoint(28));
+        let snapshot = run.state.snapshot();
+        run.state.run(0).unwrap();
+        assert_eq!(run.value("counter"), 3);
+        run.state.restore(&snapshot);
+        assert!(run.state.is_paused());
+        assert_eq!(run.state.program_counter, 28);
+        assert_eq!(run.value("counter"), 2);
+        // The restored processor runs on exactly like the original did
+        run.state.run(0).unwrap();
+        assert_eq!(run.value("counter"), 3);
+        assert_eq!(run.state.mode, TransientMode::HALTED(None));
+    }
+
+    /// Runs a memcpy or memset line with `$to` and `$from` holding the addresses of `$dest` and `$source`
+    fn run_with_pointers(declarations: &str, operation: &str) -> TestRun {
+        let source = |to: usize, from: usize| format!("{}set32 $to {}\nset32 $from {}\n{}\nhlt64", declarations, to, from, operation);
+        // The pointers don't change the layout, so compiling once finds the addresses
+        let symbols = read_symbols(&compile(&source(0, 0)).unwrap()).unwrap();
+        let address = |name: &str| symbols.iter().find(|(x, ..)| x == name).unwrap().1;
+        run(&source(address("dest"), address("source")))
+    }
+
+    #[test]
+    fn memcpy_copies_between_addresses() {
+        let declarations = declare_bytes("source", &[1, 2, 3, 4]) + &declare_bytes("dest", &[0; 4]) + "set64 $len 3\n";
+        let run = run_with_pointers(&declarations, "memcpy $to $from $len");
+        assert_eq!(run.bytes("dest", 4), [1, 2, 3, 0]);
+        assert_eq!(run.bytes("source", 4), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn memset_fills_bytes() {
+        let declarations = declare_bytes("dest", &[0; 4]) + "set8 $source 0\nset8 $value 7\nset64 $len 3\n";
+        let run = run_with_pointers(&declarations, "memset $to $value $len");
+        assert_eq!(run.bytes("dest", 4), [7, 7, 7, 0]);
+    }
+
+    #[test]
+    fn swap_exchanges_two_variables() {
+        let run = run("set32 $a 1234\nset32 $b 5678\nswap32 $a $b\nhlt64");
+        assert_eq!(run.value("a"), 5678);
+        assert_eq!(run.value("b"), 1234);
+    }
+
+    #[test]
+    fn bswap_reverses_the_bytes_of_the_operand_size() {
+        let run = run("
+            set16 $short 4660
+            set32 $int 287454020
+            set64 $long 1
+            set16 $short_swapped 0
+            set32 $int_swapped 0
+            set64 $long_swapped 0
+            bswap16 $short $short_swapped
+            bswap32 $int $int_swapped
+            bswap64 $long $long_swapped
+            hlt64
+        ");
+        assert_eq!(run.value("short_swapped"), 0x3412);
+        assert_eq!(run.value("int_swapped"), 0x44332211);
+        assert_eq!(run.value("long_swapped"), 1 << 56);
+    }
+
+    #[test]
+    fn bit_counts_stay_within_the_operand_size() {
+        let run = run("
+            set16 $value 240
+            set16 $zero 0
+            set16 $popcnt 0
+            set16 $clz 0
+            set16 $ctz 0
+            set16 $clz_zero 0
+            set16 $ctz_zero 0
+            popcnt16 $value $popcnt
+            clz16 $value $clz
+            ctz16 $value $ctz
+            clz16 $zero $clz_zero
+            ctz16 $zero $ctz_zero
+            hlt64
+        ");
+        assert_eq!(run.value("popcnt"), 4);
+        assert_eq!(run.value("clz"), 8);
+        assert_eq!(run.value("ctz"), 4);
+        assert_eq!(run.value("clz_zero"), 16);
+        assert_eq!(run.value("ctz_zero"), 16);
+    }
+
+    fn rotate(operation: &str, value: u64, amount: u64) -> u64 {
+        run(&format!("set8 $value {}\nset8 $amount {}\nset8 $result 0\n{} $value $amount $result\nhlt64", value, amount, operation)).value("result")
+    }
+
+    #[test]
+    fn rotl_wraps_bits_around_to_the_right() {
+        assert_eq!(rotate("rotl8", 0x81, 1), 0x03);
+        assert_eq!(rotate("rotl8", 0x81, 4), 0x18);
+        assert_eq!(rotate("rotl8", 0x81, 0), 0x81);
+        assert_eq!(rotate("rotl8", 0x81, 8), 0x81);
+    }
+
+    #[test]
+    fn rotr_wraps_bits_around_to_the_left() {
+        assert_eq!(rotate("rotr8", 0x81, 1), 0xC0);
+        assert_eq!(rotate("rotr8", 0x81, 4), 0x18);
+        assert_eq!(rotate("rotr8", 0x81, 9), 0xC0);
+        // Rotating by nothing or by the whole width leaves the value alone
+        assert_eq!(rotate("rotr8", 0x81, 0), 0x81);
+        assert_eq!(rotate("rotr8", 0x81, 8), 0x81);
+        let run = run("set64 $value 1\nset64 $amount 64\nset64 $result 0\nrotr64 $value $amount $result\nhlt64");
+        assert_eq!(run.value("result"), 1);
+    }
+
+    #[test]
+    fn inc_and_dec_wrap_around() {
+        let run = run("
+            set8 $full 255
+            set8 $empty 0
+            set16 $value 1000
+            inc8 $full
+            dec8 $empty
+            inc16 $value
+            inc16 $value
+            dec16 $value
+            hlt64
+        ");
+        assert_eq!(run.value("full"), 0);
+        assert_eq!(run.value("empty"), 255);
+        assert_eq!(run.value("value"), 1001);
+    }
+
+    #[test]
+    fn neg_negates_in_twos_complement() {
+        let run = run("
+            set8 $one 1
+            set8 $zero 0
+            set8 $minimum 128
+            set8 $negated_one 0
+            set8 $negated_zero 9
+            set8 $negated_minimum 0
+            neg8 $one $negated_one
+            neg8 $zero $negated_zero
+            neg8 $minimum $negated_minimum
+            hlt64
+        ");
+        assert_eq!(run.value("negated_one"), 255);
+        assert_eq!(run.value("negated_zero"), 0);
+        assert_eq!(run.value("negated_minimum"), 128);
+    }
+
+    #[test]
+    fn abs_reads_the_operand_as_signed() {
+        let run = run("
+            set8 $negative 249
+            set8 $positive 7
+            set8 $maximum 127
+            set8 $a 0
+            set8 $b 0
+            set8 $c 0
+            abs8 $negative $a
+            abs8 $positive $b
+            abs8 $maximum $c
+            hlt64
+        ");
+        assert_eq!(run.value("a"), 7);
+        assert_eq!(run.value("b"), 7);
+        assert_eq!(run.value("c"), 127);
+    }
+
+    #[test]
+    fn abs_of_the_minimum_value_overflows() {
+        let run = try_run_with_input("set8 $minimum 128\nset8 $result 0\nabs8 $minimum $result\nhlt64", &[]);
+        assert!(matches!(run.result, Err(TransientError::Overflow)));
+        let run = try_run_with_input("set64 $minimum 9223372036854775808\nset64 $result 0\nabs64 $minimum $result\nhlt64", &[]);
+        assert!(matches!(run.result, Err(TransientError::Overflow)));
+    }
+
+    #[test]
+    fn assert_and_nassert_check_the_operand() {
+        let run = run("set8 $one 1\nset8 $zero 0\nassert8 $one\nnassert8 $zero\nhlt64");
+        assert_eq!(run.state.mode, TransientMode::HALTED(None));
+        let failed = try_run_with_input("set8 $zero 0\nassert8 $zero\nhlt64", &[]);
+        assert!(matches!(failed.result, Err(TransientError::AssertionFailed { addr, pc: 0 }) if addr == failed.address("zero")));
+        let failed = try_run_with_input("set8 $one 1\nset8 $after 0\nnassert8 $one\nnassert8 $after\nhlt64", &[]);
+        assert!(matches!(failed.result, Err(TransientError::AssertionFailed { addr, pc: 0 }) if addr == failed.address("one")));
+    }
+
+    #[test]
+    fn assertions_can_be_disabled() {
+        let run = run_with("set8 $zero 0\nassert8 $zero\nhlt64", |state| state.debug_assertions = false);
+        assert_eq!(run.state.mode, TransientMode::HALTED(None));
+    }
+
+    #[test]
+    fn trap_pauses_only_with_a_hook() {
+        let source = "set8 $counter 0\ntrap\ninc8 $counter\nhlt64";
+        let unhooked = run(source);
+        assert_eq!(unhooked.value("counter"), 1);
+        let mut executed = Rc::default();
+        let mut hooked = run_with(source, |state| executed = record_instructions(state));
+        assert!(hooked.state.is_paused());
+        assert_eq!(hooked.state.program_counter, 1);
+        assert_eq!(hooked.value("counter"), 0);
+        hooked.state.run(0).unwrap();
+        assert_eq!(hooked.value("counter"), 1);
+        assert_eq!(*executed.borrow(), [(TRAP, 0), (INC, 1), (HLT, 15)]);
+    }
+
+    #[test]
+    fn bounds_includes_both_limits() {
+        let check = |value: u64| {
+            let source = format!("set16 $value {}\nset16 $low 10\nset16 $high 20\nset16 $result 9\nbounds16 $value $low $high $result\nhlt64", value);
+            run(&source).value("result")
+        };
+        assert_eq!(check(9), 0);
+        assert_eq!(check(10), 1);
+        assert_eq!(check(15), 1);
+        assert_eq!(check(20), 1);
+        assert_eq!(check(21), 0);
+    }
+
+    fn compare(operation: &str, a: u64, b: u64) -> u64 {
+        run(&format!("set8 $a {}\nset8 $b {}\nset8 $result 9\n{} $a $b $result\nhlt64", a, b, operation)).value("result")
+    }
+
+    #[test]
+    fn signed_comparisons_read_the_operands_as_signed() {
+        // 255 is -1 and 128 is -128 as signed bytes
+        assert_eq!(compare("scgt8", 1, 255), 1);
+        assert_eq!(compare("scgt8", 255, 1), 0);
+        assert_eq!(compare("scgt8", 127, 128), 1);
+        assert_eq!(compare("scgt8", 5, 5), 0);
+        assert_eq!(compare("sclt8", 255, 1), 1);
+        assert_eq!(compare("sclt8", 128, 127), 1);
+        assert_eq!(compare("sclt8", 1, 255), 0);
+        assert_eq!(compare("sclt8", 5, 5), 0);
+        assert_eq!(compare("sequ8", 255, 255), 1);
+        assert_eq!(compare("sequ8", 255, 1), 0);
+    }
+
+    /// Returns the result and the overflow flag of a checked arithmetic operation
+    fn checked(operation: &str, size: u8, a: u64, b: u64) -> (u64, u64) {
+        let run = run(&format!(
+            "set{0} $a {1}\nset{0} $b {2}\nset{0} $result 0\nset{0} $overflow 9\n{3}{0} $a $b $result $overflow\nhlt64",
+            size, a, b, operation
+        ));
+        (run.value("result"), run.value("overflow"))
+    }
+
+    #[test]
+    fn checked_arithmetic_flags_overflow_of_the_operand_size() {
+        assert_eq!(checked("addo", 8, 100, 27), (127, 0));
+        assert_eq!(checked("addo", 8, 200, 100), (44, 1));
+        assert_eq!(checked("addo", 16, 65535, 1), (0, 1));
+        assert_eq!(checked("addo", 64, u64::MAX, 1), (0, 1));
+        assert_eq!(checked("subo", 8, 5, 3), (2, 0));
+        assert_eq!(checked("subo", 8, 3, 5), (254, 1));
+        assert_eq!(checked("mulo", 8, 15, 17), (255, 0));
+        assert_eq!(checked("mulo", 16, 300, 300), (90000 % 65536, 1));
+        assert_eq!(checked("mulo", 32, 65536, 65536), (0, 1));
+        assert_eq!(checked("mulo", 64, 1 << 32, 1 << 32), (0, 1));
+    }
+
+    fn saturating(operation: &str, a: u64, b: u64, signed: bool) -> u64 {
+        let source = format!("set8 $a {}\nset8 $b {}\nset8 $result 0\n{} $a $b $result\nhlt64", a, b, operation);
+        run_with(&source, |state| state.signed_arithmetic = signed).value("result")
+    }
+
+    #[test]
+    fn saturating_arithmetic_clamps_to_the_unsigned_range() {
+        assert_eq!(saturating("sadd8", 200, 100, false), 255);
+        assert_eq!(saturating("sadd8", 20, 10, false), 30);
+        assert_eq!(saturating("ssub8", 10, 20, false), 0);
+        assert_eq!(saturating("smul8", 16, 16, false), 255);
+        assert_eq!(saturating("smul8", 15, 17, false), 255);
+    }
+
+    #[test]
+    fn saturating_arithmetic_clamps_to_the_signed_range() {
+        // 100 + 100 stops at 127, -100 - 100 at -128
+        assert_eq!(saturating("sadd8", 100, 100, true), 127);
+        assert_eq!(saturating("sadd8", 156, 156, true), 128);
+        assert_eq!(saturating("sadd8", 255, 1, true), 0);
+        assert_eq!(saturating("ssub8", 156, 100, true), 128);
+        assert_eq!(saturating("ssub8", 100, 156, true), 127);
+        assert_eq!(saturating("ssub8", 10, 20, true), 246);
+        assert_eq!(saturating("smul8", 16, 16, true), 127);
+        assert_eq!(saturating("smul8", 240, 16, true), 128);
+    }
+
+    #[test]
+    fn min_and_max_follow_the_signedness() {
+        let pick = |operation: &str, signed: bool| {
+            let source = format!("set8 $a 255\nset8 $b 1\nset8 $result 0\n{} $a $b $result\nhlt64", operation);
+            run_with(&source, |state| state.signed_arithmetic = signed).value("result")
+        };
+        assert_eq!(pick("min8", false), 1);
+        assert_eq!(pick("max8", false), 255);
+        // 255 is -1 as a signed byte
+        assert_eq!(pick("min8", true), 255);
+        assert_eq!(pick("max8", true), 1);
+    }
+
+    fn modulo(a: u64, b: u64) -> u64 {
+        run(&format!("set8 $a {}\nset8 $b {}\nset8 $result 9\nmod8 $a $b $result\nhlt64", a, b)).value("result")
+    }
+
+    #[test]
+    fn mod_is_never_negative() {
+        assert_eq!(modulo(7, 3), 1);
+        // -7 mod 3 and -7 mod -3
+        assert_eq!(modulo(249, 3), 2);
+        assert_eq!(modulo(249, 253), 2);
+        // 7 mod -3
+        assert_eq!(modulo(7, 253), 1);
+        assert_eq!(modulo(250, 3), 0);
+    }
+
+    #[test]
+    fn mod_rejects_division_by_zero() {
+        let run = try_run_with_input("set8 $a 7\nset8 $b 0\nset8 $result 0\nmod8 $a $b $result\nhlt64", &[]);
+        assert!(matches!(run.result, Err(TransientError::DivisionByZero)));
+    }
+
+    #[test]
+    fn imm_stores_the_embedded_value() {
+        let run = run("
+            set8 $byte 0
+            set16 $short 0
+            set64 $long 0
+            imm8 200 $byte
+            imm16 4660 $short
+            imm64 18446744073709551615 $long
+            hlt64
+        ");
+        assert_eq!(run.value("byte"), 200);
+        assert_eq!(run.value("short"), 4660);
+        assert_eq!(run.value("long"), u64::MAX);
+    }
+
+    #[test]
+    fn repeat_walks_the_destination_of_imm() {
+        let source = declare_bytes("a", &[1, 2, 3, 4, 5, 6, 7, 8]) + "
+            set64 $count 3
+            rep $count
+            imm16 258 $a
+            hlt64
+        ";
+        assert_eq!(run(&source).bytes("a", 8), [1, 2, 1, 2, 1, 2, 7, 8]);
+    }
+
+    #[test]
+    fn hlte_halts_with_the_exit_code() {
+        let halted = run("set8 $code 3\nset8 $after 1\nhlte8 $code\nputi8 $after\nhlt64");
+        assert_eq!(halted.state.mode, TransientMode::HALTED(Some(3)));
+        assert_eq!(halted.console.contents(), "");
+        let largest = run("set64 $code 2147483647\nhlte64 $code");
+        assert_eq!(largest.state.mode, TransientMode::HALTED(Some(i32::MAX)));
+    }
+
+    #[test]
+    fn hlte_rejects_exit_codes_that_dont_fit() {
+        let run = try_run_with_input("set64 $code 2147483648\nhlte64 $code", &[]);
+        assert!(matches!(run.result, Err(TransientError::InvalidOperand(_))));
+        let run = try_run_with_input("set64 $code 18446744073709551615\nhlte64 $code", &[]);
+        assert!(matches!(run.result, Err(TransientError::InvalidOperand(_))));
+    }
+
+    #[test]
+    fn random_follows_the_seeded_generator() {
+        let run = run("set64 $first 0\nset64 $second 0\nset8 $byte 0\nrand64 $first\nrand64 $second\nrand8 $byte\nhlt64");
+        // The test processor is seeded with 1
+        assert_eq!(run.value("first"), xorshift64(1));
+        assert_eq!(run.value("second"), xorshift64(xorshift64(1)));
+        assert_eq!(run.value("byte"), xorshift64(xorshift64(xorshift64(1))) & 0xFF);
+        assert_eq!(run.state.rng_state, xorshift64(xorshift64(xorshift64(1))));
+    }
+
+    #[test]
+    fn clock_counts_up() {
+        let run = run("set64 $before 0\nset64 $after 0\nclock64 $before\nclock64 $after\nhlt64");
+        assert!(run.value("before") > 0);
+        assert!(run.value("after") >= run.value("before"));
+    }
+
+    #[test]
+    fn puth_pads_to_the_operand_size() {
+        let run = run("
+            set8 $byte 255
+            set16 $short 31
+            set32 $int 3054
+            set64 $long 0
+            puth8 $byte
+            puth16 $short
+            puth32 $int
+            puth64 $long
+            hlt64
+        ");
+        assert_eq!(run.console.contents(), "0xFF0x001F0x00000BEE0x0000000000000000");
+    }
+
+    #[test]
+    fn strlen_stops_at_the_terminator() {
+        let run = run("setstr $text \"hello\"\nsetstr $empty \"\"\nset32 $length 9\nset32 $empty_length 9\nstrlen $text $length\nstrlen $empty $empty_length\nhlt64");
+        assert_eq!(run.value("length"), 5);
+        assert_eq!(run.value("empty_length"), 0);
+    }
+
+    fn compare_strings(a: &str, b: &str) -> u64 {
+        run(&format!("setstr $a \"{}\"\nsetstr $b \"{}\"\nset8 $result 9\nstrcmp $a $b $result\nhlt64", a, b)).value("result")
+    }
+
+    #[test]
+    fn strcmp_orders_strings() {
+        assert_eq!(compare_strings("apple", "apple"), 0);
+        assert_eq!(compare_strings("apple", "banana"), 255);
+        assert_eq!(compare_strings("banana", "apple"), 1);
+        // A prefix sorts first
+        assert_eq!(compare_strings("app", "apple"), 255);
+        assert_eq!(compare_strings("apple", "app"), 1);
+        assert_eq!(compare_strings("Zebra", "apple"), 255);
+    }
+}